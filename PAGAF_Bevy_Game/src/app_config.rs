@@ -1,13 +1,30 @@
 use bevy::prelude::*;
-use bevy::audio::{AddAudioSource, AudioLoader, AudioPlugin, AudioSource};
 
+/// Entities spawned with `StateScoped` are despawned when leaving their state.
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
+#[states(scoped_entities)]
 pub enum GameState {
+    /// Waits for the tile models before showing the main menu.
     #[default]
+    Loading,
     MainMenu,
     LoadGame,
     Settings,
     InGame,
+    /// Large generated map used to measure rendering performance.
+    Benchmark,
+}
+
+/// Overlays shown over the running game. The scene is kept while they are open.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, SubStates)]
+#[source(GameState = GameState::InGame)]
+pub enum InGameState {
+    #[default]
+    Playing,
+    /// Input, placement and virtual time are stopped.
+    Paused,
+    /// Settings opened from the pause menu, the game stays paused.
+    Settings,
 }
 
 #[derive(Resource)]
@@ -0,0 +1,196 @@
+use crate::app_config::GameState;
+use crate::game::CameraRig;
+use crate::tile_loader::{TileAssets, TileCatalogue};
+use crate::tilemap::{cell_hash, tile_bundle, tile_scene_bundle};
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+/// Side of the benchmark map, in cells.
+const BENCHMARK_SIZE: usize = 200;
+
+/// Stress test: a `BENCHMARK_SIZE`² map filled with tiles, with entity counts and frame time.
+#[derive(Resource)]
+pub struct Benchmark {
+    /// Spawn every tile as a scene hierarchy, the way tiles were spawned before.
+    pub use_scenes: bool,
+    seed: u64,
+    tiles: Option<Entity>,
+}
+
+impl Default for Benchmark {
+    fn default() -> Self {
+        Self {
+            use_scenes: false,
+            seed: rand::random(),
+            tiles: None,
+        }
+    }
+}
+
+pub fn setup_benchmark(
+    mut commands: Commands,
+    mut benchmark: ResMut<Benchmark>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    catalogue: Res<TileCatalogue>,
+    tile_assets: Res<TileAssets>,
+) {
+    let size = BENCHMARK_SIZE as f32;
+    let centre = (size - 1.0) / 2.0;
+
+    let rig = CameraRig::new(Vec3::new(centre, 0.0, centre), Vec3::new(0.0, 60.0, 60.0))
+        .with_bounds(Rect::new(0.0, 0.0, size - 1.0, size - 1.0));
+    commands.spawn((
+        Camera3d::default(),
+        rig.transform(),
+        rig,
+        StateScoped(GameState::Benchmark),
+    ));
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform::from_xyz(10.0, 20.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+        StateScoped(GameState::Benchmark),
+    ));
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(size, size))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::BLACK,
+            perceptual_roughness: 1.0,
+            ..default()
+        })),
+        Transform::from_xyz(centre, 0.0, centre),
+        StateScoped(GameState::Benchmark),
+    ));
+
+    benchmark.tiles = Some(spawn_benchmark_tiles(
+        &mut commands,
+        &benchmark,
+        &catalogue,
+        &tile_assets,
+    ));
+}
+
+/// Fills the map with single-cell tiles picked from the cell hash.
+fn spawn_benchmark_tiles(
+    commands: &mut Commands,
+    benchmark: &Benchmark,
+    catalogue: &TileCatalogue,
+    tile_assets: &TileAssets,
+) -> Entity {
+    let tiles: Vec<_> = catalogue
+        .placeable()
+        .filter(|tile| catalogue.get(*tile).footprint() == UVec2::ONE)
+        .collect();
+    let seed = benchmark.seed;
+    let use_scenes = benchmark.use_scenes;
+
+    commands
+        .spawn((
+            Transform::default(),
+            Visibility::default(),
+            StateScoped(GameState::Benchmark),
+        ))
+        .with_children(|parent| {
+            for z in 0..BENCHMARK_SIZE {
+                for x in 0..BENCHMARK_SIZE {
+                    let cell = IVec2::new(x as i32, z as i32);
+                    let tile_type = tiles[(cell_hash(!seed, cell) % tiles.len() as u64) as usize];
+                    let translation = Vec3::new(x as f32, 0.0, z as f32);
+                    // Turned like buildings placed by the player, for both spawn paths alike
+                    let rotation = (cell_hash(seed.rotate_left(32), cell) % 4) as u8;
+                    if use_scenes {
+                        parent.spawn(tile_scene_bundle(
+                            catalogue,
+                            tile_assets,
+                            tile_type,
+                            seed,
+                            cell,
+                            translation,
+                            rotation,
+                        ));
+                    } else {
+                        parent.spawn(tile_bundle(
+                            catalogue,
+                            tile_assets,
+                            tile_type,
+                            seed,
+                            cell,
+                            translation,
+                            rotation,
+                        ));
+                    }
+                }
+            }
+        })
+        .id()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn benchmark_panel(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    mut benchmark: ResMut<Benchmark>,
+    mut next_state: ResMut<NextState<GameState>>,
+    diagnostics: Res<DiagnosticsStore>,
+    catalogue: Res<TileCatalogue>,
+    tile_assets: Res<TileAssets>,
+    scoped: Query<(Entity, &StateScoped<GameState>)>,
+    children: Query<&Children>,
+    meshes: Query<(), With<Mesh3d>>,
+) {
+    // Only what the benchmark spawned, the rest of the world is hidden
+    let entities: Vec<Entity> = scoped
+        .iter()
+        .filter(|(_, scope)| scope.0 == GameState::Benchmark)
+        .flat_map(|(entity, _)| std::iter::once(entity).chain(children.iter_descendants(entity)))
+        .collect();
+    let mesh_count = entities.iter().filter(|&&e| meshes.contains(e)).count();
+
+    let frame_time = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FRAME_TIME)
+        .and_then(|d| d.smoothed())
+        .unwrap_or_default();
+    let fps = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|d| d.smoothed())
+        .unwrap_or_default();
+
+    let mut respawn = false;
+    egui::Window::new("Benchmark")
+        .anchor(egui::Align2::LEFT_TOP, egui::vec2(10.0, 10.0))
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!("Map: {0}×{0}", BENCHMARK_SIZE));
+            ui.label(format!("Entities: {}", entities.len()));
+            ui.label(format!("Mesh entities: {}", mesh_count));
+            ui.label(format!("Frame time: {:.2} ms ({:.0} FPS)", frame_time, fps));
+            ui.separator();
+            respawn = ui
+                .checkbox(&mut benchmark.use_scenes, "Spawn tiles as scenes")
+                .changed();
+            if ui.button("Back").clicked() {
+                next_state.set(GameState::MainMenu);
+            }
+        });
+
+    if respawn {
+        if let Some(tiles) = benchmark.tiles.take() {
+            commands.entity(tiles).despawn();
+        }
+        benchmark.tiles = Some(spawn_benchmark_tiles(
+            &mut commands,
+            &benchmark,
+            &catalogue,
+            &tile_assets,
+        ));
+    }
+}
+
+/// Benchmark entities are state scoped, only the handle to the despawned tiles is left.
+pub fn cleanup_benchmark(mut benchmark: ResMut<Benchmark>) {
+    benchmark.tiles = None;
+}
@@ -0,0 +1,757 @@
+use crate::app_config::GameState;
+use crate::budget::Budget;
+use crate::picking::HoveredCell;
+use crate::tile_loader::{TileAssets, TileCatalogue};
+use crate::tilemap::{HighlightMaterials, SelectedTile, TileMap, TileType, tile_bundle};
+use crate::undo_redo::{Action, UndoRedo};
+use crate::wfc::WFCState;
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+use serde::{Deserialize, Serialize};
+
+/// Blueprint library, next to the `tile_packs` folder. Shared by every map and game.
+pub const BLUEPRINTS_PATH: &str = "blueprints.ron";
+
+/// A building of a blueprint, anchored relative to the top-left corner of the blueprint.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BlueprintTile {
+    pub x: u32,
+    pub z: u32,
+    /// Catalogue id, indices change when tile packs are added.
+    pub tile: String,
+    #[serde(default)]
+    pub rotation: u8,
+}
+
+/// Buildings copied from a region of the `TileMap`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Blueprint {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub tiles: Vec<BlueprintTile>,
+}
+
+impl Blueprint {
+    /// Copies the buildings lying entirely between the cells `min` and `max`, included.
+    pub fn copy(tile_map: &TileMap, catalogue: &TileCatalogue, min: UVec2, max: UVec2) -> Self {
+        let mut tiles = Vec::new();
+        for z in min.y..=max.y {
+            for x in min.x..=max.x {
+                let tile = &tile_map.tiles[z as usize][x as usize];
+                let definition = catalogue.get(tile.tile_type);
+                let end = UVec2::new(x, z) + definition.footprint() - UVec2::ONE;
+                if tile.tile_type != TileType::EMPTY
+                    && tile.anchor == tile.position
+                    && end.cmple(max).all()
+                {
+                    tiles.push(BlueprintTile {
+                        x: x - min.x,
+                        z: z - min.y,
+                        tile: definition.id.clone(),
+                        rotation: tile.rotation,
+                    });
+                }
+            }
+        }
+        Self {
+            name: String::new(),
+            width: max.x - min.x + 1,
+            height: max.y - min.y + 1,
+            tiles,
+        }
+    }
+
+    /// Size once turned by `rotation` quarter turns.
+    pub fn size(&self, rotation: u8) -> UVec2 {
+        if rotation.is_multiple_of(2) {
+            UVec2::new(self.width, self.height)
+        } else {
+            UVec2::new(self.height, self.width)
+        }
+    }
+
+    /// Buildings mirrored along x, then turned clockwise by `rotation` quarter turns.
+    /// Gives their anchor relative to the top-left corner, their type and model rotation.
+    /// Non-square buildings cannot take an odd number of quarter turns and are left out,
+    /// as are tiles missing from the catalogue.
+    pub fn transformed(
+        &self,
+        catalogue: &TileCatalogue,
+        rotation: u8,
+        mirrored: bool,
+    ) -> Vec<(UVec2, TileType, u8)> {
+        let rotation = rotation % 4;
+        let mut buildings = Vec::new();
+        for tile in &self.tiles {
+            let Some(tile_type) = catalogue.find(&tile.tile) else {
+                continue;
+            };
+            let definition = catalogue.get(tile_type);
+            if rotation % 2 == 1 && !definition.rotates() {
+                continue;
+            }
+
+            // Both corners of the footprint are moved, the anchor is the top-left one
+            let mut extent = IVec2::new(self.width as i32, self.height as i32);
+            let mut min = IVec2::new(tile.x as i32, tile.z as i32);
+            let mut max = min + definition.footprint().as_ivec2() - IVec2::ONE;
+            if mirrored {
+                (min.x, max.x) = (extent.x - 1 - max.x, extent.x - 1 - min.x);
+            }
+            for _ in 0..rotation {
+                let a = IVec2::new(extent.y - 1 - min.y, min.x);
+                let b = IVec2::new(extent.y - 1 - max.y, max.x);
+                (min, max) = (a.min(b), a.max(b));
+                extent = IVec2::new(extent.y, extent.x);
+            }
+
+            // Models are not mirrored, a mirrored turn goes the other way
+            let model_rotation = if mirrored {
+                (4 - tile.rotation % 4) % 4
+            } else {
+                tile.rotation % 4
+            };
+            buildings.push((min.as_uvec2(), tile_type, (model_rotation + rotation) % 4));
+        }
+        buildings
+    }
+
+    /// Number of non-square buildings `transformed` leaves out at `rotation`.
+    pub fn unturnable(&self, catalogue: &TileCatalogue, rotation: u8) -> usize {
+        if rotation.is_multiple_of(2) {
+            return 0;
+        }
+        self.tiles
+            .iter()
+            .filter_map(|tile| catalogue.find(&tile.tile))
+            .filter(|&tile_type| !catalogue.get(tile_type).rotates())
+            .count()
+    }
+}
+
+/// Saved blueprints, read at startup from `BLUEPRINTS_PATH`.
+#[derive(Resource, Default)]
+pub struct BlueprintLibrary {
+    pub blueprints: Vec<Blueprint>,
+    /// Last problem reading or writing the library file.
+    pub error: Option<String>,
+}
+
+impl BlueprintLibrary {
+    /// Adds a blueprint, replacing the one with the same name, and writes the library.
+    pub fn add(&mut self, blueprint: Blueprint) {
+        match self
+            .blueprints
+            .iter_mut()
+            .find(|b| b.name == blueprint.name)
+        {
+            Some(existing) => *existing = blueprint,
+            None => self.blueprints.push(blueprint),
+        }
+        self.save();
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.blueprints.remove(index);
+        self.save();
+    }
+
+    /// Writes the library file. The web build keeps blueprints for the session only.
+    fn save(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let path =
+                bevy::asset::io::file::FileAssetReader::get_base_path().join(BLUEPRINTS_PATH);
+            let result = ron::ser::to_string_pretty(&self.blueprints, Default::default())
+                .map_err(|e| e.to_string())
+                .and_then(|ron| std::fs::write(&path, ron).map_err(|e| e.to_string()));
+            self.error = result
+                .err()
+                .map(|e| format!("Cannot save {}: {}", BLUEPRINTS_PATH, e));
+        }
+    }
+}
+
+pub fn load_blueprints(mut commands: Commands) {
+    let mut library = BlueprintLibrary::default();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let path = bevy::asset::io::file::FileAssetReader::get_base_path().join(BLUEPRINTS_PATH);
+        // No file yet is an empty library
+        if let Ok(ron) = std::fs::read_to_string(&path) {
+            match ron::from_str(&ron) {
+                Ok(blueprints) => library.blueprints = blueprints,
+                Err(e) => library.error = Some(format!("Cannot read {}: {}", BLUEPRINTS_PATH, e)),
+            }
+        }
+    }
+
+    if let Some(error) = &library.error {
+        warn!("{}", error);
+    }
+    commands.insert_resource(library);
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlueprintMode {
+    #[default]
+    Off,
+    /// Dragging over the map selects a region to copy.
+    Selecting,
+    /// Clicking the map stamps the clipboard.
+    Pasting,
+}
+
+/// Region selection, clipboard and paste options.
+#[derive(Resource, Default)]
+pub struct BlueprintTool {
+    pub mode: BlueprintMode,
+    /// Corners of the selected region, in any order.
+    region: Option<(UVec2, UVec2)>,
+    dragging: bool,
+    clipboard: Option<Blueprint>,
+    /// Quarter turns clockwise applied when pasting.
+    rotation: u8,
+    mirrored: bool,
+    /// Result of the last paste.
+    status: Option<String>,
+}
+
+impl BlueprintTool {
+    pub fn is_active(&self) -> bool {
+        self.mode != BlueprintMode::Off
+    }
+
+    /// Top-left and bottom-right cells of the selected region.
+    fn region(&self) -> Option<(UVec2, UVec2)> {
+        self.region.map(|(a, b)| (a.min(b), a.max(b)))
+    }
+
+    fn copy(&mut self, tile_map: &TileMap, catalogue: &TileCatalogue) {
+        if let Some((min, max)) = self.region() {
+            let blueprint = Blueprint::copy(tile_map, catalogue, min, max);
+            self.status = Some(format!("Copied {} buildings", blueprint.tiles.len()));
+            self.clipboard = Some(blueprint);
+        }
+    }
+
+    fn start_pasting(&mut self, blueprint: Blueprint) {
+        self.clipboard = Some(blueprint);
+        self.mode = BlueprintMode::Pasting;
+        self.rotation = 0;
+        self.mirrored = false;
+        self.status = None;
+    }
+}
+
+// Places each building that fits, and can be paid for when there is a `budget` to pay from
+#[allow(clippy::too_many_arguments)]
+fn place_buildings(
+    commands: &mut Commands,
+    tile_map: &mut TileMap,
+    wfc_state: &mut WFCState,
+    catalogue: &TileCatalogue,
+    tile_assets: &TileAssets,
+    mut budget: Option<&mut Budget>,
+    buildings: &[(UVec2, TileType, u8)],
+    corner: UVec2,
+) -> Vec<Action> {
+    let mut actions = Vec::new();
+    for &(offset, tile_type, rotation) in buildings {
+        let cell = corner + offset;
+        let (x, z) = (cell.x as usize, cell.y as usize);
+        let definition = catalogue.get(tile_type);
+        if budget
+            .as_deref()
+            .is_none_or(|budget| budget.can_afford(definition))
+            && wfc_state.grid.can_place_tile(x, z, tile_type)
+            && wfc_state.grid.place_tile(x, z, tile_type)
+        {
+            if let Some(budget) = budget.as_deref_mut() {
+                budget.charge(definition);
+            }
+            tile_map.set_tile(commands, tile_assets, x, z, tile_type, rotation);
+            actions.push(Action::PlaceTile(x, z, tile_type, rotation));
+        }
+    }
+    actions
+}
+
+/// Places the clipboard with its top-left corner on `corner`, each building that fits and
+/// can be paid for. The whole paste is a single undo step. Returns how many buildings were placed.
+#[allow(clippy::too_many_arguments)]
+pub fn paste(
+    commands: &mut Commands,
+    tile_map: &mut TileMap,
+    wfc_state: &mut WFCState,
+    catalogue: &TileCatalogue,
+    tile_assets: &TileAssets,
+    undo_redo: &mut UndoRedo,
+    budget: &mut Budget,
+    buildings: &[(UVec2, TileType, u8)],
+    corner: UVec2,
+) -> usize {
+    let actions = place_buildings(
+        commands,
+        tile_map,
+        wfc_state,
+        catalogue,
+        tile_assets,
+        Some(budget),
+        buildings,
+        corner,
+    );
+    let placed = actions.len();
+    if placed > 0 {
+        undo_redo.add_action(Action::Group(actions));
+    }
+    placed
+}
+
+/// Places the buildings of a saved map for free, they were paid for when first built.
+/// Not an undo step. Returns how many buildings were placed.
+pub fn rebuild(
+    commands: &mut Commands,
+    tile_map: &mut TileMap,
+    wfc_state: &mut WFCState,
+    catalogue: &TileCatalogue,
+    tile_assets: &TileAssets,
+    buildings: &[(UVec2, TileType, u8)],
+) -> usize {
+    place_buildings(
+        commands,
+        tile_map,
+        wfc_state,
+        catalogue,
+        tile_assets,
+        None,
+        buildings,
+        UVec2::ZERO,
+    )
+    .len()
+}
+
+/// Ctrl+C / Ctrl+V, R to rotate and M to mirror the paste, Escape or right click to stop.
+/// Dragging selects a region, clicking stamps the clipboard.
+#[allow(clippy::too_many_arguments)]
+pub fn blueprint_input(
+    mut commands: Commands,
+    mut tool: ResMut<BlueprintTool>,
+    mut selected_tile: ResMut<SelectedTile>,
+    hovered: Res<HoveredCell>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut tile_map: ResMut<TileMap>,
+    mut wfc_state: ResMut<WFCState>,
+    mut undo_redo: ResMut<UndoRedo>,
+    mut budget: ResMut<Budget>,
+    catalogue: Res<TileCatalogue>,
+    tile_assets: Res<TileAssets>,
+    mut egui_contexts: EguiContexts,
+) {
+    // Picking a tile in the building panel leaves the tool
+    if selected_tile.is_changed() && selected_tile.0 != TileType::EMPTY && tool.is_active() {
+        tool.mode = BlueprintMode::Off;
+        return;
+    }
+
+    let ctx = egui_contexts.ctx_mut();
+    if !ctx.wants_keyboard_input() {
+        let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+        if ctrl && keys.just_pressed(KeyCode::KeyC) {
+            tool.copy(&tile_map, &catalogue);
+        }
+        if ctrl
+            && keys.just_pressed(KeyCode::KeyV)
+            && let Some(blueprint) = tool.clipboard.clone()
+        {
+            tool.start_pasting(blueprint);
+            selected_tile.0 = TileType::EMPTY;
+        }
+        if tool.mode == BlueprintMode::Pasting {
+            if keys.just_pressed(KeyCode::KeyR) {
+                tool.rotation = (tool.rotation + 1) % 4;
+            }
+            if keys.just_pressed(KeyCode::KeyM) {
+                tool.mirrored = !tool.mirrored;
+            }
+        }
+        if keys.just_pressed(KeyCode::Escape) && tool.is_active() {
+            tool.mode = BlueprintMode::Off;
+        }
+    }
+
+    if ctx.wants_pointer_input() {
+        return;
+    }
+    let cell = hovered.0.map(|(x, z)| UVec2::new(x as u32, z as u32));
+    match tool.mode {
+        BlueprintMode::Off => {}
+        BlueprintMode::Selecting => {
+            if mouse.just_pressed(MouseButton::Left)
+                && let Some(cell) = cell
+            {
+                tool.region = Some((cell, cell));
+                tool.dragging = true;
+            } else if mouse.just_released(MouseButton::Left) {
+                tool.dragging = false;
+            } else if tool.dragging
+                && let (Some(cell), Some((start, end))) = (cell, tool.region)
+                && cell != end
+            {
+                tool.region = Some((start, cell));
+            }
+        }
+        BlueprintMode::Pasting => {
+            if mouse.just_pressed(MouseButton::Right) {
+                tool.mode = BlueprintMode::Off;
+            } else if mouse.just_pressed(MouseButton::Left)
+                && let (Some(cell), Some(blueprint)) = (cell, &tool.clipboard)
+            {
+                let buildings = blueprint.transformed(&catalogue, tool.rotation, tool.mirrored);
+                let placed = paste(
+                    &mut commands,
+                    &mut tile_map,
+                    &mut wfc_state,
+                    &catalogue,
+                    &tile_assets,
+                    &mut undo_redo,
+                    &mut budget,
+                    &buildings,
+                    cell,
+                );
+                let mut status =
+                    format!("Placed {} of {} buildings", placed, blueprint.tiles.len());
+                let unturnable = blueprint.unturnable(&catalogue, tool.rotation);
+                if unturnable > 0 {
+                    status += &format!(", {} non-square ones cannot be turned by 90°", unturnable);
+                }
+                tool.status = Some(status);
+            }
+        }
+    }
+}
+
+pub fn blueprint_panel(
+    mut contexts: EguiContexts,
+    mut tool: ResMut<BlueprintTool>,
+    mut library: ResMut<BlueprintLibrary>,
+    mut selected_tile: ResMut<SelectedTile>,
+    tile_map: Res<TileMap>,
+    catalogue: Res<TileCatalogue>,
+    mut name: Local<String>,
+) {
+    let mode = tool.mode;
+    let mut new_mode = None;
+    let mut copy = false;
+    let mut paste_from = None;
+    let mut rotate = false;
+    let mut mirrored = tool.mirrored;
+    let mut save = false;
+    let mut delete = None;
+
+    egui::Window::new("Blueprints")
+        .anchor(egui::Align2::LEFT_CENTER, egui::vec2(10.0, 0.0))
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                if ui
+                    .selectable_label(mode == BlueprintMode::Selecting, "⬚ Select")
+                    .clicked()
+                {
+                    new_mode = Some(if mode == BlueprintMode::Selecting {
+                        BlueprintMode::Off
+                    } else {
+                        BlueprintMode::Selecting
+                    });
+                }
+                copy = ui
+                    .add_enabled(tool.region.is_some(), egui::Button::new("Copy"))
+                    .on_hover_text("Ctrl+C")
+                    .clicked();
+                if ui
+                    .add_enabled(tool.clipboard.is_some(), egui::Button::new("Paste"))
+                    .on_hover_text("Ctrl+V")
+                    .clicked()
+                {
+                    paste_from = tool.clipboard.clone();
+                }
+            });
+
+            match mode {
+                BlueprintMode::Off => {}
+                BlueprintMode::Selecting => match tool.region() {
+                    Some((min, max)) => {
+                        let size = max - min + UVec2::ONE;
+                        ui.label(format!("Selected {}×{} cells", size.x, size.y));
+                    }
+                    None => {
+                        ui.label("Drag over the map to select a region");
+                    }
+                },
+                BlueprintMode::Pasting => {
+                    ui.label("Click to stamp, Escape to stop");
+                    ui.horizontal(|ui| {
+                        rotate = ui.button("⟳ Rotate").on_hover_text("R").clicked();
+                        ui.checkbox(&mut mirrored, "Mirror").on_hover_text("M");
+                        if let Some(blueprint) = &tool.clipboard {
+                            let size = blueprint.size(tool.rotation);
+                            ui.label(format!(
+                                "{}°, {}×{}",
+                                tool.rotation as u32 * 90,
+                                size.x,
+                                size.y
+                            ));
+                        }
+                    });
+                    if let Some(blueprint) = &tool.clipboard {
+                        let unturnable = blueprint.unturnable(&catalogue, tool.rotation);
+                        if unturnable > 0 {
+                            ui.colored_label(
+                                egui::Color32::LIGHT_RED,
+                                format!(
+                                    "{} non-square buildings are left out at this rotation",
+                                    unturnable
+                                ),
+                            );
+                        }
+                    }
+                }
+            }
+            if let Some(status) = &tool.status {
+                ui.label(status);
+            }
+
+            if tool.clipboard.is_some() {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut *name)
+                            .hint_text("Blueprint name")
+                            .desired_width(120.0),
+                    );
+                    save = ui
+                        .add_enabled(!name.trim().is_empty(), egui::Button::new("Save"))
+                        .clicked();
+                });
+            }
+
+            ui.separator();
+            ui.label("Library");
+            if library.blueprints.is_empty() {
+                ui.label("No saved blueprints");
+            }
+            for (i, blueprint) in library.blueprints.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{} ({}×{}, {} buildings)",
+                        blueprint.name,
+                        blueprint.width,
+                        blueprint.height,
+                        blueprint.tiles.len()
+                    ));
+                    if ui.button("Paste").clicked() {
+                        paste_from = Some(blueprint.clone());
+                    }
+                    if ui.button("🗑").clicked() {
+                        delete = Some(i);
+                    }
+                });
+            }
+            if let Some(error) = &library.error {
+                ui.colored_label(egui::Color32::LIGHT_RED, error);
+            }
+        });
+
+    // Only touch the resources on actions, the preview is rebuilt when the tool changes
+    let started = new_mode.is_some() || paste_from.is_some();
+    if let Some(mode) = new_mode {
+        tool.mode = mode;
+    }
+    if copy {
+        tool.copy(&tile_map, &catalogue);
+    }
+    if let Some(blueprint) = paste_from {
+        tool.start_pasting(blueprint);
+    }
+    if rotate {
+        tool.rotation = (tool.rotation + 1) % 4;
+    }
+    if mirrored != tool.mirrored {
+        tool.mirrored = mirrored;
+    }
+    if started && tool.is_active() {
+        selected_tile.0 = TileType::EMPTY;
+    }
+    if save && let Some(clipboard) = &tool.clipboard {
+        library.add(Blueprint {
+            name: name.trim().to_string(),
+            ..clipboard.clone()
+        });
+        name.clear();
+    }
+    if let Some(i) = delete {
+        library.remove(i);
+    }
+}
+
+/// Outlines the selected region, or shows the clipboard on the hovered cell with every
+/// building coloured by whether it can be placed there.
+#[allow(clippy::too_many_arguments)]
+pub fn blueprint_preview(
+    mut commands: Commands,
+    tool: Res<BlueprintTool>,
+    hovered: Res<HoveredCell>,
+    tile_map: Res<TileMap>,
+    wfc_state: Res<WFCState>,
+    catalogue: Res<TileCatalogue>,
+    tile_assets: Res<TileAssets>,
+    highlight_materials: Res<HighlightMaterials>,
+    mut preview: Local<Option<Entity>>,
+) {
+    if !(tool.is_changed() || hovered.is_changed() || wfc_state.is_changed()) {
+        return;
+    }
+    // Already gone when the previous game ended
+    if let Some(entity) = preview.take() {
+        commands.entity(entity).try_despawn();
+    }
+
+    let cell_quad = |material: &Handle<StandardMaterial>, x: u32, z: u32| {
+        (
+            Mesh3d(highlight_materials.cell_mesh.clone()),
+            MeshMaterial3d(material.clone()),
+            Transform::from_xyz(x as f32, 0.03, z as f32),
+        )
+    };
+
+    match tool.mode {
+        BlueprintMode::Off => {}
+        BlueprintMode::Selecting => {
+            let Some((min, max)) = tool.region() else {
+                return;
+            };
+            let root = commands
+                .spawn((
+                    Transform::default(),
+                    Visibility::default(),
+                    StateScoped(GameState::InGame),
+                ))
+                .with_children(|parent| {
+                    for z in min.y..=max.y {
+                        for x in min.x..=max.x {
+                            parent.spawn(cell_quad(&highlight_materials.preview, x, z));
+                        }
+                    }
+                })
+                .id();
+            *preview = Some(root);
+        }
+        BlueprintMode::Pasting => {
+            let (Some((x, z)), Some(blueprint)) = (hovered.0, &tool.clipboard) else {
+                return;
+            };
+            let corner = UVec2::new(x as u32, z as u32);
+            let root = commands
+                .spawn((
+                    Transform::from_xyz(x as f32, 0.01, z as f32),
+                    Visibility::default(),
+                    StateScoped(GameState::InGame),
+                ))
+                .with_children(|parent| {
+                    for (offset, tile_type, rotation) in
+                        blueprint.transformed(&catalogue, tool.rotation, tool.mirrored)
+                    {
+                        let cell = corner + offset;
+                        let material = if wfc_state.grid.can_place_tile(
+                            cell.x as usize,
+                            cell.y as usize,
+                            tile_type,
+                        ) {
+                            &highlight_materials.valid
+                        } else {
+                            &highlight_materials.invalid
+                        };
+                        let definition = catalogue.get(tile_type);
+                        parent.spawn(tile_bundle(
+                            &catalogue,
+                            &tile_assets,
+                            tile_type,
+                            tile_map.seed,
+                            cell.as_ivec2(),
+                            Vec3::new(offset.x as f32, 0.0, offset.y as f32)
+                                + definition.footprint_centre(),
+                            rotation,
+                        ));
+                        for (cx, cz) in
+                            definition.footprint_cells(offset.x as usize, offset.y as usize)
+                        {
+                            parent.spawn(cell_quad(material, cx as u32, cz as u32));
+                        }
+                    }
+                })
+                .id();
+            *preview = Some(root);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn building(x: u32, z: u32, tile: &str) -> BlueprintTile {
+        BlueprintTile {
+            x,
+            z,
+            tile: tile.to_string(),
+            rotation: 1,
+        }
+    }
+
+    #[test]
+    fn test_transformed() {
+        // A house above a 3×1 mall
+        let blueprint = Blueprint {
+            name: "block".to_string(),
+            width: 3,
+            height: 2,
+            tiles: vec![building(0, 0, "residential"), building(0, 1, "mall")],
+        };
+        let catalogue = TileCatalogue::builtin();
+        let residential = catalogue.find("residential").unwrap();
+        let mall = catalogue.find("mall").unwrap();
+
+        assert_eq!(
+            blueprint.transformed(&catalogue, 0, false),
+            vec![
+                (UVec2::new(0, 0), residential, 1),
+                (UVec2::new(0, 1), mall, 1)
+            ]
+        );
+        // The mall cannot be turned a quarter
+        assert_eq!(blueprint.size(1), UVec2::new(2, 3));
+        assert_eq!(
+            blueprint.transformed(&catalogue, 1, false),
+            vec![(UVec2::new(1, 0), residential, 2)]
+        );
+        assert_eq!(blueprint.unturnable(&catalogue, 1), 1);
+        assert_eq!(blueprint.unturnable(&catalogue, 2), 0);
+        assert_eq!(
+            blueprint.transformed(&catalogue, 2, false),
+            vec![
+                (UVec2::new(2, 1), residential, 3),
+                (UVec2::new(0, 0), mall, 3)
+            ]
+        );
+        assert_eq!(
+            blueprint.transformed(&catalogue, 0, true),
+            vec![
+                (UVec2::new(2, 0), residential, 3),
+                (UVec2::new(0, 1), mall, 3)
+            ]
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use crate::simulation::SimulationClock;
+use crate::tile_loader::{TileCatalogue, TileCategory, TileDefinition};
+use crate::tilemap::{TileMap, TileType};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+use serde::{Deserialize, Serialize};
+
+/// Treasury of a new city.
+pub const STARTING_FUNDS: i64 = 10_000;
+/// Highest tax rate offered in the budget panel, in percent.
+const MAX_TAX_RATE: u32 = 20;
+
+/// Monthly tax on residential, commercial and industrial buildings, in percent of their cost.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TaxRates {
+    pub residential: u32,
+    pub commercial: u32,
+    pub industrial: u32,
+}
+
+impl TaxRates {
+    /// Rate for buildings of `category`, 0 for the untaxed ones.
+    pub fn get(&self, category: TileCategory) -> u32 {
+        match category {
+            TileCategory::Residential => self.residential,
+            TileCategory::Commercial => self.commercial,
+            TileCategory::Industrial => self.industrial,
+            _ => 0,
+        }
+    }
+}
+
+impl Default for TaxRates {
+    fn default() -> Self {
+        Self {
+            residential: 9,
+            commercial: 9,
+            industrial: 9,
+        }
+    }
+}
+
+/// Income and expenses over a month.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MonthlyReport {
+    pub taxes: i64,
+    pub upkeep: i64,
+}
+
+impl MonthlyReport {
+    pub fn net(&self) -> i64 {
+        self.taxes - self.upkeep
+    }
+}
+
+/// City treasury. Construction is paid from it, taxes and upkeep are settled every month.
+#[derive(Resource)]
+pub struct Budget {
+    pub funds: i64,
+    pub tax_rates: TaxRates,
+    /// What the last full month brought.
+    pub last_month: MonthlyReport,
+    /// Why the last construction was refused.
+    pub refused: Option<String>,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self {
+            funds: STARTING_FUNDS,
+            tax_rates: TaxRates::default(),
+            last_month: MonthlyReport::default(),
+            refused: None,
+        }
+    }
+}
+
+impl Budget {
+    pub fn can_afford(&self, tile: &TileDefinition) -> bool {
+        self.funds >= tile.cost as i64
+    }
+
+    /// Why `tile` cannot be paid for, if it cannot.
+    pub fn shortfall(&self, tile: &TileDefinition) -> Option<String> {
+        (!self.can_afford(tile)).then(|| {
+            format!(
+                "Not enough money for {}: it costs {} $, the treasury has {} $",
+                tile.name, tile.cost, self.funds
+            )
+        })
+    }
+
+    /// Pays for building `tile`, or records why it cannot be built.
+    pub fn charge(&mut self, tile: &TileDefinition) -> bool {
+        if let Some(shortfall) = self.shortfall(tile) {
+            self.refused = Some(shortfall);
+            return false;
+        }
+        self.pay(tile);
+        self.refused = None;
+        true
+    }
+
+    /// Pays for building `tile` even if it puts the treasury into debt.
+    pub fn pay(&mut self, tile: &TileDefinition) {
+        self.funds -= tile.cost as i64;
+    }
+
+    /// Pays back the construction of `tile`, when it is undone.
+    pub fn refund(&mut self, tile: &TileDefinition) {
+        self.funds += tile.cost as i64;
+    }
+
+    /// Taxes and upkeep of the buildings on the map over a month.
+    pub fn forecast(&self, tile_map: &TileMap, catalogue: &TileCatalogue) -> MonthlyReport {
+        let mut report = MonthlyReport::default();
+        for tile in tile_map.tiles.iter().flatten() {
+            if tile.tile_type == TileType::EMPTY || tile.anchor != tile.position {
+                continue;
+            }
+            let definition = catalogue.get(tile.tile_type);
+            report.taxes +=
+                definition.cost as i64 * self.tax_rates.get(definition.category) as i64 / 100;
+            report.upkeep += definition.upkeep as i64;
+        }
+        report
+    }
+}
+
+/// Collects taxes and pays upkeep on the first tick of every month.
+pub fn settle_budget(
+    clock: Res<SimulationClock>,
+    tile_map: Res<TileMap>,
+    catalogue: Res<TileCatalogue>,
+    mut budget: ResMut<Budget>,
+) {
+    if !clock.is_new_month() {
+        return;
+    }
+    let report = budget.forecast(&tile_map, &catalogue);
+    budget.funds += report.net();
+    budget.last_month = report;
+}
+
+fn money(amount: i64) -> egui::RichText {
+    let text = egui::RichText::new(format!("{} $", amount));
+    if amount < 0 {
+        text.color(egui::Color32::LIGHT_RED)
+    } else {
+        text
+    }
+}
+
+/// Treasury under the clock, with the monthly balance and the tax rates.
+pub fn budget_panel(
+    mut contexts: EguiContexts,
+    mut budget: ResMut<Budget>,
+    tile_map: Res<TileMap>,
+    catalogue: Res<TileCatalogue>,
+) {
+    let forecast = budget.forecast(&tile_map, &catalogue);
+    let mut tax_rates = budget.tax_rates;
+
+    // The treasury stays visible in the title while the window is collapsed
+    egui::Window::new(format!("💰 {} $", budget.funds))
+        .id(egui::Id::new("budget"))
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 50.0))
+        .resizable(false)
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            if let Some(refused) = &budget.refused {
+                ui.colored_label(egui::Color32::LIGHT_RED, refused);
+                ui.separator();
+            }
+
+            egui::Grid::new("budget_report").show(ui, |ui| {
+                ui.label("");
+                ui.label("Last month");
+                ui.label("Next month");
+                ui.end_row();
+                ui.label("Taxes");
+                ui.label(money(budget.last_month.taxes));
+                ui.label(money(forecast.taxes));
+                ui.end_row();
+                ui.label("Upkeep");
+                ui.label(money(-budget.last_month.upkeep));
+                ui.label(money(-forecast.upkeep));
+                ui.end_row();
+                ui.label("Balance");
+                ui.label(money(budget.last_month.net()));
+                ui.label(money(forecast.net()));
+                ui.end_row();
+            });
+            ui.separator();
+
+            ui.label("Tax rates, of the building cost per month");
+            for (name, rate) in [
+                ("Residential", &mut tax_rates.residential),
+                ("Commercial", &mut tax_rates.commercial),
+                ("Industrial", &mut tax_rates.industrial),
+            ] {
+                ui.add(
+                    egui::Slider::new(rate, 0..=MAX_TAX_RATE)
+                        .suffix("%")
+                        .text(name),
+                );
+            }
+        });
+
+    if tax_rates != budget.tax_rates {
+        budget.tax_rates = tax_rates;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_charge() {
+        let catalogue = TileCatalogue::builtin();
+        let stadium = catalogue.get(catalogue.find("stadium").unwrap());
+        let mut budget = Budget {
+            funds: 2500,
+            ..default()
+        };
+
+        assert!(budget.charge(stadium));
+        assert_eq!(budget.funds, 500);
+        assert!(!budget.charge(stadium));
+        assert_eq!(budget.funds, 500);
+        assert!(budget.refused.is_some());
+
+        budget.refund(stadium);
+        assert!(budget.charge(stadium));
+        assert!(budget.refused.is_none());
+    }
+}
@@ -0,0 +1,307 @@
+use crate::tile_loader::{TileAssets, TileCatalogue};
+use crate::tilemap::{GridTile, PlacementHighlight, TileType, tile_bundle};
+use crate::wfc::{EAST, NORTH, SOUTH, WEST, WFCError, WFCGrid};
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+/* ─────────────────────────────  Constants  ──────────────────────────────── */
+
+pub const CHUNK_SIZE: usize = 16;
+
+/// Chunks closer than this (in chunks) to the camera focus are generated and shown.
+const LOAD_RADIUS: i32 = 2;
+/// Loaded chunks further than this are despawned. Their tiles are kept.
+const UNLOAD_RADIUS: i32 = 4;
+/// New chunks generated per frame, so moving fast doesn't stall a frame.
+const CHUNKS_PER_FRAME: usize = 1;
+/// Generation attempts before a chunk is given up on.
+const MAX_ATTEMPTS: usize = 5;
+
+/// Which kind of map the game is played on.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WorldMode {
+    /// The fixed 50×50 `TileMap`.
+    #[default]
+    Finite,
+    /// Chunks generated on demand around the camera.
+    Infinite,
+}
+
+pub fn is_infinite(mode: Res<WorldMode>) -> bool {
+    *mode == WorldMode::Infinite
+}
+
+pub struct Chunk {
+    /// Row-major, `CHUNK_SIZE * CHUNK_SIZE` tiles.
+    pub tiles: Vec<TileType>,
+    /// Root entity while the chunk is spawned.
+    pub root: Option<Entity>,
+}
+
+impl Chunk {
+    pub fn tile(&self, x: usize, z: usize) -> TileType {
+        self.tiles[z * CHUNK_SIZE + x]
+    }
+}
+
+/// Tile data of every generated chunk, loaded or not.
+#[derive(Resource)]
+pub struct InfiniteWorld {
+    pub chunks: HashMap<IVec2, Chunk>,
+    /// Picks the model variant and jitter of each cell.
+    pub seed: u64,
+}
+
+impl Default for InfiniteWorld {
+    fn default() -> Self {
+        Self {
+            chunks: HashMap::new(),
+            seed: rand::random(),
+        }
+    }
+}
+
+impl InfiniteWorld {
+    /// Generates the chunk at `coord`, using the edges of the already generated
+    /// neighbouring chunks as boundary constraints.
+    /// An edge cell that no tile can match is left unconstrained, the chunk then shows a seam
+    /// there. Only the collapse is retried after a contradiction, the edges would fail the same way.
+    fn generate_chunk(
+        &self,
+        catalogue: &TileCatalogue,
+        coord: IVec2,
+    ) -> Result<Vec<TileType>, WFCError> {
+        let last = CHUNK_SIZE - 1;
+        let mut grid = WFCGrid::new(CHUNK_SIZE, CHUNK_SIZE, catalogue);
+
+        let mut edges = Vec::new();
+        for i in 0..CHUNK_SIZE {
+            if let Some(north) = self.chunks.get(&(coord + IVec2::NEG_Y)) {
+                edges.push((i, 0, NORTH, north.tile(i, last)));
+            }
+            if let Some(south) = self.chunks.get(&(coord + IVec2::Y)) {
+                edges.push((i, last, SOUTH, south.tile(i, 0)));
+            }
+            if let Some(west) = self.chunks.get(&(coord + IVec2::NEG_X)) {
+                edges.push((0, i, WEST, west.tile(last, i)));
+            }
+            if let Some(east) = self.chunks.get(&(coord + IVec2::X)) {
+                edges.push((last, i, EAST, east.tile(0, i)));
+            }
+        }
+        for (x, z, dir, tile) in edges {
+            let cells = grid.cells.clone();
+            if let Err(err) = grid.constrain(x, z, dir, tile) {
+                warn!(
+                    "Chunk {}: cell ({}, {}) cannot match its neighbour, its edge is relaxed: {}",
+                    coord, x, z, err
+                );
+                grid.cells = cells;
+            }
+        }
+
+        let mut attempts = 0;
+        let grid = loop {
+            let mut attempt = grid.clone();
+            match attempt.generate() {
+                Ok(()) => break attempt,
+                Err(err) => {
+                    attempts += 1;
+                    if attempts == MAX_ATTEMPTS {
+                        return Err(err);
+                    }
+                    warn!("Chunk {} failed to generate, retrying: {}", coord, err);
+                }
+            }
+        };
+
+        let mut tiles = Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE);
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                tiles.push(grid.tile_at(x, z).unwrap_or(TileType::EMPTY));
+            }
+        }
+        Ok(tiles)
+    }
+}
+
+#[derive(Resource)]
+pub struct ChunkAssets {
+    ground_mesh: Handle<Mesh>,
+    ground_material: Handle<StandardMaterial>,
+}
+
+pub fn setup_chunks(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let size = CHUNK_SIZE as f32;
+    commands.insert_resource(ChunkAssets {
+        ground_mesh: meshes.add(Plane3d::default().mesh().size(size, size)),
+        ground_material: materials.add(StandardMaterial {
+            base_color: Color::BLACK,
+            perceptual_roughness: 1.0,
+            ..default()
+        }),
+    });
+}
+
+/// Shows the finite map ground only when playing on the finite map.
+pub fn apply_world_mode(mode: Res<WorldMode>, mut ground: Query<&mut Visibility, With<GridTile>>) {
+    let visibility = match *mode {
+        WorldMode::Finite => Visibility::Inherited,
+        WorldMode::Infinite => Visibility::Hidden,
+    };
+    for mut v in ground.iter_mut() {
+        *v = visibility;
+    }
+}
+
+/// Leaving the game drops the infinite world, the next one gets a new seed. The finite map
+/// ground and highlights stay spawned but are hidden until the next finite game.
+#[allow(clippy::type_complexity)]
+pub fn unload_world(
+    mut commands: Commands,
+    mut world: ResMut<InfiniteWorld>,
+    mut map: Query<&mut Visibility, Or<(With<GridTile>, With<PlacementHighlight>)>>,
+) {
+    for root in world
+        .chunks
+        .values_mut()
+        .filter_map(|chunk| chunk.root.take())
+    {
+        commands.entity(root).despawn();
+    }
+    *world = InfiniteWorld::default();
+    for mut visibility in map.iter_mut() {
+        visibility.set_if_neq(Visibility::Hidden);
+    }
+}
+
+/// Chunk the camera is looking at, from the centre of the viewport.
+fn focus_chunk(camera: &Camera, camera_transform: &GlobalTransform) -> Option<IVec2> {
+    let centre = camera.logical_viewport_size()? / 2.0;
+    let ray = camera.viewport_to_world(camera_transform, centre).ok()?;
+    let distance = ray.intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Y))?;
+    let focus = ray.get_point(distance);
+    Some(IVec2::new(
+        (focus.x / CHUNK_SIZE as f32).floor() as i32,
+        (focus.z / CHUNK_SIZE as f32).floor() as i32,
+    ))
+}
+
+/// Generates, spawns and unloads chunks as the camera moves.
+pub fn update_chunks(
+    mut commands: Commands,
+    mut world: ResMut<InfiniteWorld>,
+    chunk_assets: Res<ChunkAssets>,
+    catalogue: Res<TileCatalogue>,
+    tile_assets: Res<TileAssets>,
+    camera: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+) {
+    let Ok((camera, camera_transform)) = camera.single() else {
+        return;
+    };
+    let Some(focus) = focus_chunk(camera, camera_transform) else {
+        return;
+    };
+
+    // Unload distant chunks, keeping their tiles
+    for (coord, chunk) in world.chunks.iter_mut() {
+        let distance = (*coord - focus).abs().max_element();
+        if distance > UNLOAD_RADIUS
+            && let Some(root) = chunk.root.take()
+        {
+            commands.entity(root).despawn();
+        }
+    }
+
+    // Nearest chunks first
+    let mut wanted = Vec::new();
+    for dz in -LOAD_RADIUS..=LOAD_RADIUS {
+        for dx in -LOAD_RADIUS..=LOAD_RADIUS {
+            wanted.push(focus + IVec2::new(dx, dz));
+        }
+    }
+    wanted.sort_by_key(|coord| (*coord - focus).length_squared());
+
+    let seed = world.seed;
+    let mut generated = 0;
+    for coord in wanted {
+        if !world.chunks.contains_key(&coord) {
+            if generated == CHUNKS_PER_FRAME {
+                continue;
+            }
+            generated += 1;
+
+            let tiles = world
+                .generate_chunk(&catalogue, coord)
+                .unwrap_or_else(|err| {
+                    error!("Giving up on chunk {}, leaving it empty: {}", coord, err);
+                    vec![TileType::EMPTY; CHUNK_SIZE * CHUNK_SIZE]
+                });
+            world.chunks.insert(coord, Chunk { tiles, root: None });
+        }
+
+        let chunk = world.chunks.get_mut(&coord).unwrap();
+        if chunk.root.is_none() {
+            chunk.root = Some(spawn_chunk(
+                &mut commands,
+                &chunk_assets,
+                &catalogue,
+                &tile_assets,
+                seed,
+                coord,
+                chunk,
+            ));
+        }
+    }
+}
+
+fn spawn_chunk(
+    commands: &mut Commands,
+    chunk_assets: &ChunkAssets,
+    catalogue: &TileCatalogue,
+    tile_assets: &TileAssets,
+    seed: u64,
+    coord: IVec2,
+    chunk: &Chunk,
+) -> Entity {
+    let origin = Vec3::new(
+        (coord.x * CHUNK_SIZE as i32) as f32,
+        0.0,
+        (coord.y * CHUNK_SIZE as i32) as f32,
+    );
+    // Cells are centred on integer coordinates, the ground plane on the chunk centre
+    let centre = (CHUNK_SIZE as f32 - 1.0) / 2.0;
+
+    commands
+        .spawn((Transform::from_translation(origin), Visibility::default()))
+        .with_children(|parent| {
+            parent.spawn((
+                Mesh3d(chunk_assets.ground_mesh.clone()),
+                MeshMaterial3d(chunk_assets.ground_material.clone()),
+                Transform::from_xyz(centre, 0.0, centre),
+            ));
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    let tile_type = chunk.tile(x, z);
+                    if tile_type != TileType::EMPTY {
+                        let local = Vec3::new(x as f32, 0.0, z as f32);
+                        let cell = coord * CHUNK_SIZE as i32 + IVec2::new(x as i32, z as i32);
+                        parent.spawn(tile_bundle(
+                            catalogue,
+                            tile_assets,
+                            tile_type,
+                            seed,
+                            cell,
+                            local,
+                            0,
+                        ));
+                    }
+                }
+            }
+        })
+        .id()
+}
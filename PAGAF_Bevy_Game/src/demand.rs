@@ -0,0 +1,220 @@
+use crate::budget::{Budget, TaxRates};
+use crate::tile_loader::{TileCatalogue, TileCategory};
+use crate::tilemap::{TileMap, TileType};
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+/// Residents housed by a residential cell.
+const RESIDENTS_PER_CELL: u32 = 20;
+/// Jobs offered by a commercial or industrial cell.
+const COMMERCIAL_JOBS_PER_CELL: u32 = 10;
+const INDUSTRIAL_JOBS_PER_CELL: u32 = 15;
+/// Share of the residents who work.
+const WORKFORCE: f32 = 0.5;
+/// Shop and factory jobs wanted per resident.
+const COMMERCIAL_PER_RESIDENT: f32 = 0.15;
+const INDUSTRIAL_PER_RESIDENT: f32 = 0.3;
+/// Newcomers drawn to the city even without jobs, so an empty map wants houses.
+const BASE_RESIDENTS: f32 = 100.0;
+/// Part of the gap between the population and the housing closed every tick.
+const GROWTH_PER_TICK: f32 = 0.01;
+/// Demand lost for each point of tax above the default rate.
+const TAX_SENSITIVITY: f32 = 0.05;
+
+/// Homes and jobs offered by the buildings of the map.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Capacity {
+    pub housing: u32,
+    pub commercial_jobs: u32,
+    pub industrial_jobs: u32,
+}
+
+impl Capacity {
+    pub fn of(tile_map: &TileMap, catalogue: &TileCatalogue) -> Self {
+        let mut capacity = Self::default();
+        for tile in tile_map.tiles.iter().flatten() {
+            if tile.tile_type == TileType::EMPTY {
+                continue;
+            }
+            // Counted per cell, large buildings offer more
+            match catalogue.get(tile.tile_type).category {
+                TileCategory::Residential => capacity.housing += RESIDENTS_PER_CELL,
+                TileCategory::Commercial => capacity.commercial_jobs += COMMERCIAL_JOBS_PER_CELL,
+                TileCategory::Industrial => capacity.industrial_jobs += INDUSTRIAL_JOBS_PER_CELL,
+                _ => {}
+            }
+        }
+        capacity
+    }
+
+    pub fn jobs(&self) -> u32 {
+        self.commercial_jobs + self.industrial_jobs
+    }
+}
+
+/// What the city needs next, from -1 (far too much) to 1 (badly needed).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Demand {
+    pub residential: f32,
+    pub commercial: f32,
+    pub industrial: f32,
+}
+
+// Positive when `needed` exceeds `offered`, relative to the larger of both
+fn shortage(needed: f32, offered: f32) -> f32 {
+    ((needed - offered) / needed.max(offered).max(1.0)).clamp(-1.0, 1.0)
+}
+
+impl Demand {
+    /// Homes are wanted for the workers the jobs need, shops and factories for the residents.
+    /// Taxes above the default rate lower the demand.
+    pub fn compute(population: f32, capacity: Capacity, tax_rates: TaxRates) -> Self {
+        let default_rates = TaxRates::default();
+        let tax = |category| {
+            (tax_rates.get(category) as f32 - default_rates.get(category) as f32) * TAX_SENSITIVITY
+        };
+        let demand = |value: f32, category| (value - tax(category)).clamp(-1.0, 1.0);
+
+        Self {
+            residential: demand(
+                shortage(
+                    capacity.jobs() as f32 / WORKFORCE + BASE_RESIDENTS,
+                    capacity.housing as f32,
+                ),
+                TileCategory::Residential,
+            ),
+            commercial: demand(
+                shortage(
+                    population * COMMERCIAL_PER_RESIDENT,
+                    capacity.commercial_jobs as f32,
+                ),
+                TileCategory::Commercial,
+            ),
+            industrial: demand(
+                shortage(
+                    population * INDUSTRIAL_PER_RESIDENT,
+                    capacity.industrial_jobs as f32,
+                ),
+                TileCategory::Industrial,
+            ),
+        }
+    }
+}
+
+/// Population, jobs and demand of the city, updated every simulation tick.
+#[derive(Resource, Default)]
+pub struct CityStats {
+    pub population: f32,
+    pub capacity: Capacity,
+    pub demand: Demand,
+}
+
+impl CityStats {
+    /// Residents with a job.
+    pub fn employed(&self) -> u32 {
+        ((self.population * WORKFORCE) as u32).min(self.capacity.jobs())
+    }
+}
+
+/// People move in while there are free homes and out when homes are demolished.
+pub fn update_city(
+    tile_map: Res<TileMap>,
+    catalogue: Res<TileCatalogue>,
+    budget: Res<Budget>,
+    mut city: ResMut<CityStats>,
+) {
+    city.capacity = Capacity::of(&tile_map, &catalogue);
+    let housing = city.capacity.housing as f32;
+    city.population += (housing - city.population) * GROWTH_PER_TICK;
+    // The last few residents move in or out at once
+    if (housing - city.population).abs() < 1.0 {
+        city.population = housing;
+    }
+    city.demand = Demand::compute(city.population, city.capacity, budget.tax_rates);
+}
+
+/// Classic RCI bars, growing up with demand and down with oversupply, with the population.
+pub fn demand_bars(ui: &mut egui::Ui, city: &CityStats) {
+    ui.vertical(|ui| {
+        let (rect, response) = ui.allocate_exact_size(egui::vec2(54.0, 48.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.hline(
+            rect.x_range(),
+            rect.center().y,
+            egui::Stroke::new(1.0, egui::Color32::GRAY),
+        );
+        let bars = [
+            ("R", city.demand.residential, TileCategory::Residential),
+            ("C", city.demand.commercial, TileCategory::Commercial),
+            ("I", city.demand.industrial, TileCategory::Industrial),
+        ];
+        for (i, (label, value, category)) in bars.into_iter().enumerate() {
+            let x = rect.left() + 4.0 + i as f32 * 18.0;
+            let half = rect.height() / 2.0 - 8.0;
+            let top = rect.center().y - value.max(0.0) * half;
+            let bottom = rect.center().y - value.min(0.0) * half;
+            let [r, g, b, _] = category.color().to_srgba().to_u8_array();
+            painter.rect_filled(
+                egui::Rect::from_x_y_ranges(x..=x + 10.0, top..=bottom),
+                0.0,
+                egui::Color32::from_rgb(r, g, b),
+            );
+            painter.text(
+                egui::pos2(x + 5.0, rect.bottom()),
+                egui::Align2::CENTER_BOTTOM,
+                label,
+                egui::FontId::proportional(10.0),
+                egui::Color32::WHITE,
+            );
+        }
+        response.on_hover_text(format!(
+            "Demand\nResidential: {:+.0}%\nCommercial: {:+.0}%\nIndustrial: {:+.0}%",
+            city.demand.residential * 100.0,
+            city.demand.commercial * 100.0,
+            city.demand.industrial * 100.0
+        ));
+
+        ui.label(format!("👥 {}", city.population.round() as u32))
+            .on_hover_text(format!(
+                "Homes: {}\nJobs: {} filled of {} ({} commercial, {} industrial)",
+                city.capacity.housing,
+                city.employed(),
+                city.capacity.jobs(),
+                city.capacity.commercial_jobs,
+                city.capacity.industrial_jobs
+            ));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_demand() {
+        // An empty city wants homes first
+        let demand = Demand::compute(0.0, Capacity::default(), TaxRates::default());
+        assert_eq!(demand.residential, 1.0);
+        assert_eq!(demand.commercial, 0.0);
+
+        // Residents without jobs want shops and factories, not more homes
+        let capacity = Capacity {
+            housing: 1000,
+            ..default()
+        };
+        let demand = Demand::compute(1000.0, capacity, TaxRates::default());
+        assert!(demand.residential < 0.0);
+        assert!(demand.commercial > 0.0 && demand.industrial > 0.0);
+
+        // Higher taxes lower the demand
+        let taxed = Demand::compute(
+            1000.0,
+            capacity,
+            TaxRates {
+                commercial: 20,
+                ..default()
+            },
+        );
+        assert!(taxed.commercial < demand.commercial);
+    }
+}
@@ -1,21 +1,157 @@
+use bevy::input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll, MouseScrollUnit};
 use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::EguiContexts;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{PI, TAU};
 
-#[derive(Resource)]
-pub struct GamePause {
-    pub paused: bool,
+use crate::app_config::GameState;
+use crate::blueprint::BlueprintTool;
+use crate::budget::Budget;
+use crate::chunks::WorldMode;
+use crate::demand::CityStats;
+use crate::generation::Generation;
+use crate::minimap::Minimap;
+use crate::picking::{HoveredCell, SelectedBuilding};
+use crate::planning::PlanningView;
+use crate::save::SaveFile;
+use crate::tile_loader::TileCatalogue;
+use crate::tilemap::{PlacementRefusal, SelectedTile, TileMap, TileType};
+use crate::undo_redo::UndoRedo;
+use crate::wfc::{WFCGrid, WFCState};
+use crate::wfc_debug::WFCDebug;
+
+/// Closest and furthest zoom, in world units from the focus point.
+const MIN_DISTANCE: f32 = 4.0;
+const MAX_DISTANCE: f32 = 80.0;
+/// Camera pitch limits, in radians above the ground.
+const MIN_PITCH: f32 = 0.2;
+const MAX_PITCH: f32 = 1.5;
+/// Width of the screen border that pans the camera, in logical pixels.
+const EDGE_MARGIN: f32 = 8.0;
+/// How fast the camera flies to a target, higher is faster.
+const FLIGHT_SPEED: f32 = 6.0;
+
+/// Where the camera rig looks from. Stored in camera bookmarks.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraView {
+    pub focus: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+}
+
+/// RTS camera: orbits a focus point on the ground at some distance.
+#[derive(Component)]
+pub struct CameraRig {
+    pub focus: Vec3,
+    /// Rotation around the vertical axis, 0 looks towards -z.
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+    /// Area the focus stays in, on the x/z plane. `None` for the infinite world.
+    pub bounds: Option<Rect>,
+    /// View the camera is smoothly moving to. Cancelled by any camera input.
+    pub flight: Option<CameraView>,
+    /// The pitch is driven by the planning view, orbiting only turns the camera.
+    pub top_down: bool,
 }
 
-impl Default for GamePause {
-    fn default() -> Self {
-        Self { paused: false }
+impl CameraRig {
+    /// Rig looking at `focus` from `focus + offset`.
+    pub fn new(focus: Vec3, offset: Vec3) -> Self {
+        Self {
+            focus,
+            yaw: offset.x.atan2(offset.z),
+            pitch: offset.y.atan2(offset.xz().length()),
+            distance: offset.length(),
+            bounds: None,
+            flight: None,
+            top_down: false,
+        }
+    }
+
+    pub fn with_bounds(mut self, bounds: Rect) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    pub fn view(&self) -> CameraView {
+        CameraView {
+            focus: self.focus,
+            yaw: self.yaw,
+            pitch: self.pitch,
+            distance: self.distance,
+        }
+    }
+
+    /// Flies to `focus` keeping the current angle and zoom.
+    pub fn fly_to(&mut self, focus: Vec3) {
+        self.flight = Some(CameraView {
+            focus,
+            ..self.view()
+        });
+    }
+
+    /// Moves a fraction `t` of the way to the flight target, ends the flight once there.
+    fn fly(&mut self, t: f32) {
+        let Some(mut target) = self.flight else {
+            return;
+        };
+        if self.top_down {
+            target.pitch = self.pitch;
+        }
+        // Turn the short way round
+        let yaw = self.yaw + (target.yaw - self.yaw + PI).rem_euclid(TAU) - PI;
+        if self.focus.distance(target.focus) < 0.01
+            && (yaw - self.yaw).abs() < 0.001
+            && (target.pitch - self.pitch).abs() < 0.001
+            && (target.distance - self.distance).abs() < 0.01
+        {
+            (self.focus, self.yaw) = (target.focus, yaw);
+            (self.pitch, self.distance) = (target.pitch, target.distance);
+            self.flight = None;
+            return;
+        }
+        self.focus = self.focus.lerp(target.focus, t);
+        self.yaw = self.yaw.lerp(yaw, t);
+        self.pitch = self.pitch.lerp(target.pitch, t);
+        self.distance = self.distance.lerp(target.distance, t);
+    }
+
+    pub fn transform(&self) -> Transform {
+        let offset = Vec3::new(
+            self.yaw.sin() * self.pitch.cos(),
+            self.pitch.sin(),
+            self.yaw.cos() * self.pitch.cos(),
+        );
+        // The facing direction is a valid up vector, even when looking straight down
+        Transform::from_translation(self.focus + offset * self.distance)
+            .looking_at(self.focus, self.axes().0)
+    }
+
+    /// Ground directions the camera is facing and to its right.
+    fn axes(&self) -> (Vec3, Vec3) {
+        let forward = Vec3::new(-self.yaw.sin(), 0.0, -self.yaw.cos());
+        let right = Vec3::new(self.yaw.cos(), 0.0, -self.yaw.sin());
+        (forward, right)
     }
 }
 
-pub fn setup_game(mut commands: Commands) {
+pub fn setup_game(mut commands: Commands, tile_map: Res<TileMap>, world_mode: Res<WorldMode>) {
     // Camera setup
+    let mut rig = CameraRig::new(Vec3::ZERO, Vec3::new(10.0, 15.0, 10.0));
+    if *world_mode == WorldMode::Finite {
+        // Cells are centred on integer coordinates
+        let max = Vec2::new(tile_map.width as f32, tile_map.height as f32) - Vec2::ONE;
+        rig = rig.with_bounds(Rect::from_corners(Vec2::ZERO, max));
+    }
     commands.spawn((
         Camera3d::default(),
-        Transform::from_xyz(10.0, 15.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+        rig.transform(),
+        rig,
+        MeshPickingCamera,
+        StateScoped(GameState::InGame),
     ));
 
     // Lighting setup
@@ -25,35 +161,267 @@ pub fn setup_game(mut commands: Commands) {
             ..default()
         },
         Transform::from_xyz(10.0, 20.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+        StateScoped(GameState::InGame),
     ));
 }
+
+/// Leaving the game throws the city away: New Game starts on an empty map and Load Game
+/// rebuilds the saved one on it.
+pub fn end_game(
+    mut commands: Commands,
+    mut tile_map: ResMut<TileMap>,
+    catalogue: Res<TileCatalogue>,
+) {
+    tile_map.clear(&mut commands);
+    tile_map.seed = rand::random();
+    commands.insert_resource(WFCState {
+        grid: WFCGrid::new(tile_map.width, tile_map.height, &catalogue),
+    });
+    commands.insert_resource(UndoRedo::default());
+    commands.insert_resource(Budget::default());
+    commands.insert_resource(CityStats::default());
+    commands.insert_resource(SelectedTile(TileType::EMPTY));
+    commands.insert_resource(PlacementRefusal::default());
+    commands.insert_resource(SelectedBuilding::default());
+    commands.insert_resource(HoveredCell::default());
+    commands.insert_resource(PlanningView::default());
+    commands.insert_resource(BlueprintTool::default());
+    commands.insert_resource(WFCDebug::default());
+    commands.insert_resource(Minimap::default());
+    commands.remove_resource::<Generation>();
+}
+
+/// WASD/arrows and screen edges pan relative to the camera facing, Q/E turn, the wheel zooms,
+/// middle drag orbits and right drag pans. Also runs flights and keeps the focus in bounds.
+#[allow(clippy::too_many_arguments)]
 pub fn camera_movement(
-    mut query: Query<&mut Transform, With<Camera3d>>,
+    mut query: Query<(&mut CameraRig, &mut Transform), With<Camera3d>>,
     input: Res<ButtonInput<KeyCode>>,
-    time: Res<Time>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    motion: Res<AccumulatedMouseMotion>,
+    scroll: Res<AccumulatedMouseScroll>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut egui_contexts: EguiContexts,
+    time: Res<Time<Real>>,
 ) {
     let speed = 10.0;
     let rotation_speed = 1.0;
+    let orbit_speed = 0.005;
+    let zoom_speed = 0.1;
 
-    if let Ok(mut transform) = query.single_mut() {
+    let Ok((mut rig, mut transform)) = query.single_mut() else {
+        return;
+    };
+    let ctx = egui_contexts.ctx_mut();
+    let (forward, right) = rig.axes();
+    // Moves faster when zoomed out
+    let pan = speed * time.delta_secs() * rig.distance / 20.0;
+
+    let mut direction = Vec3::ZERO;
+    let mut turn = 0.0;
+    if !ctx.wants_keyboard_input() {
         if input.pressed(KeyCode::ArrowLeft) || input.pressed(KeyCode::KeyA) {
-            transform.translation.x -= speed * time.delta_secs();
+            direction -= right;
         }
         if input.pressed(KeyCode::ArrowRight) || input.pressed(KeyCode::KeyD) {
-            transform.translation.x += speed * time.delta_secs();
+            direction += right;
         }
         if input.pressed(KeyCode::ArrowUp) || input.pressed(KeyCode::KeyW) {
-            transform.translation.z -= speed * time.delta_secs();
+            direction += forward;
         }
         if input.pressed(KeyCode::ArrowDown) || input.pressed(KeyCode::KeyS) {
-            transform.translation.z += speed * time.delta_secs();
+            direction -= forward;
         }
 
         if input.pressed(KeyCode::KeyQ) {
-            transform.rotate_y(rotation_speed * time.delta_secs());
+            turn += rotation_speed * time.delta_secs();
         }
         if input.pressed(KeyCode::KeyE) {
-            transform.rotate_y(-rotation_speed * time.delta_secs());
+            turn -= rotation_speed * time.delta_secs();
+        }
+    }
+
+    if let Ok(window) = windows.single()
+        && window.focused
+        && let Some(cursor) = window.cursor_position()
+    {
+        let size = window.size();
+        if cursor.x < EDGE_MARGIN {
+            direction -= right;
+        } else if cursor.x > size.x - EDGE_MARGIN {
+            direction += right;
+        }
+        if cursor.y < EDGE_MARGIN {
+            direction += forward;
+        } else if cursor.y > size.y - EDGE_MARGIN {
+            direction -= forward;
         }
     }
+    if direction != Vec3::ZERO {
+        rig.focus += direction.normalize() * pan;
+    }
+    if turn != 0.0 {
+        rig.yaw += turn;
+    }
+    let mut moved = direction != Vec3::ZERO || turn != 0.0;
+
+    if !ctx.wants_pointer_input() {
+        let lines = match scroll.unit {
+            MouseScrollUnit::Line => scroll.delta.y,
+            MouseScrollUnit::Pixel => scroll.delta.y / 16.0,
+        };
+        if lines != 0.0 {
+            rig.distance =
+                (rig.distance * (1.0 - lines * zoom_speed)).clamp(MIN_DISTANCE, MAX_DISTANCE);
+            moved = true;
+        }
+
+        let delta = motion.delta;
+        if delta != Vec2::ZERO {
+            if mouse.pressed(MouseButton::Middle) {
+                rig.yaw -= delta.x * orbit_speed;
+                if !rig.top_down {
+                    rig.pitch = (rig.pitch + delta.y * orbit_speed).clamp(MIN_PITCH, MAX_PITCH);
+                }
+                moved = true;
+            } else if mouse.pressed(MouseButton::Right) {
+                // Drags the ground along with the pointer
+                let grab = rig.distance * 0.002;
+                rig.focus += (forward * delta.y - right * delta.x) * grab;
+                moved = true;
+            }
+        }
+    }
+
+    if moved {
+        rig.flight = None;
+    } else if rig.flight.is_some() {
+        let t = 1.0 - (-FLIGHT_SPEED * time.delta_secs()).exp();
+        rig.fly(t);
+    }
+
+    if let Some(bounds) = rig.bounds {
+        let focus = rig.focus.xz().clamp(bounds.min, bounds.max);
+        if focus != rig.focus.xz() {
+            rig.focus = Vec3::new(focus.x, rig.focus.y, focus.y);
+        }
+    }
+
+    if rig.is_changed() {
+        *transform = rig.transform();
+    }
+}
+
+/// F1–F4 fly to a camera bookmark, Ctrl+F1–F4 store the current view in the save file.
+pub fn camera_bookmarks(
+    mut rigs: Query<&mut CameraRig>,
+    input: Res<ButtonInput<KeyCode>>,
+    mut save_file: ResMut<SaveFile>,
+    mut egui_contexts: EguiContexts,
+) {
+    if egui_contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    let Ok(mut rig) = rigs.single_mut() else {
+        return;
+    };
+    let ctrl = input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    for (i, key) in [KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4]
+        .into_iter()
+        .enumerate()
+    {
+        if !input.just_pressed(key) {
+            continue;
+        }
+        if ctrl {
+            save_file.bookmarks[i] = Some(rig.view());
+            match save_file.write() {
+                Ok(()) => info!("Camera bookmark {} saved", i + 1),
+                Err(err) => error!("{}", err),
+            }
+        } else if let Some(view) = save_file.bookmarks[i] {
+            rig.flight = Some(view);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunks::{Chunk, InfiniteWorld, unload_world};
+    use crate::picking::{PlacedTile, SelectionMarker};
+    use crate::tile_loader::TileAssets;
+    use crate::tilemap::CellChanged;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::state::app::StatesPlugin;
+
+    #[test]
+    fn test_benchmark_starts_without_the_city() {
+        let catalogue = TileCatalogue::builtin();
+        let mut app = App::new();
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .add_event::<CellChanged>()
+            .insert_resource(TileMap::new(50, 50, &catalogue))
+            .insert_resource(InfiniteWorld::default())
+            .insert_resource(TileAssets {
+                scenes: vec![Vec::new(); catalogue.len()],
+                models: Vec::new(),
+            })
+            .insert_resource(catalogue.clone())
+            .add_systems(OnExit(GameState::InGame), (end_game, unload_world));
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::InGame);
+        app.update();
+
+        // A building, a loaded chunk and a selection marker
+        app.world_mut()
+            .run_system_once(
+                |mut commands: Commands,
+                 mut tile_map: ResMut<TileMap>,
+                 mut world: ResMut<InfiniteWorld>,
+                 catalogue: Res<TileCatalogue>,
+                 tile_assets: Res<TileAssets>| {
+                    let tile_type = catalogue.placeable().next().unwrap();
+                    tile_map.set_tile(&mut commands, &tile_assets, 2, 3, tile_type, 0);
+                    let root = commands.spawn(Transform::default()).id();
+                    world.chunks.insert(
+                        IVec2::ZERO,
+                        Chunk {
+                            tiles: Vec::new(),
+                            root: Some(root),
+                        },
+                    );
+                    commands.spawn((SelectionMarker, StateScoped(GameState::InGame)));
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            app.world_mut()
+                .query::<&PlacedTile>()
+                .iter(app.world())
+                .count(),
+            1
+        );
+
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Benchmark);
+        app.update();
+
+        assert_eq!(
+            app.world_mut().query::<Entity>().iter(app.world()).count(),
+            0
+        );
+        assert!(app.world().resource::<InfiniteWorld>().chunks.is_empty());
+        let tile_map = app.world().resource::<TileMap>();
+        assert!(
+            tile_map
+                .tiles
+                .iter()
+                .flatten()
+                .all(|tile| tile.tile_type == TileType::EMPTY)
+        );
+    }
 }
@@ -0,0 +1,323 @@
+use crate::tile_loader::TileAssets;
+use crate::tilemap::{TileMap, TileType};
+use crate::wfc::{WFCError, WFCGrid, WFCState};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::tasks::{AsyncComputeTaskPool, Task, block_on, poll_once};
+
+/// Maximum number of finished cells spawned per frame, so the frame never stalls.
+const SPAWN_BUDGET: usize = 256;
+
+/// Generation attempts before a contradiction is given up on, as for the chunks.
+const MAX_ATTEMPTS: usize = 5;
+
+/// Number of cells collapsed per frame by the single-threaded (wasm) fallback.
+#[cfg(target_arch = "wasm32")]
+const WASM_STEP_BUDGET: usize = 64;
+
+/// Cells finished by the current attempt, and the number of that attempt.
+#[derive(Default)]
+struct Finished {
+    attempt: usize,
+    cells: Vec<(usize, usize, TileType)>,
+}
+
+/// State shared between the generation worker and the main thread.
+#[derive(Default)]
+struct Shared {
+    cancel: AtomicBool,
+    collapsed: AtomicUsize,
+    finished: Mutex<Finished>,
+}
+
+impl Shared {
+    // Drops the cells of a failed attempt, the main thread removes the ones already spawned.
+    // False once every attempt failed
+    fn restart(&self, err: &WFCError) -> bool {
+        let mut finished = self.finished.lock().unwrap();
+        warn!(
+            "Generation attempt {} failed: {}",
+            finished.attempt + 1,
+            err
+        );
+        if finished.attempt + 1 >= MAX_ATTEMPTS {
+            return false;
+        }
+        finished.attempt += 1;
+        finished.cells.clear();
+        self.collapsed.store(0, Ordering::Relaxed);
+        true
+    }
+}
+
+/// Running WFC generation. Exists only while a generation is in progress.
+#[derive(Resource)]
+pub struct Generation {
+    pub total: usize,
+    shared: Arc<Shared>,
+    // Cells spawned so far by the attempt `attempt`, removed again if it fails
+    spawned: Vec<(usize, usize)>,
+    attempt: usize,
+    #[cfg(not(target_arch = "wasm32"))]
+    task: Task<Result<WFCGrid, WFCError>>,
+    #[cfg(target_arch = "wasm32")]
+    grid: WFCGrid,
+    // Grid every attempt starts from
+    #[cfg(target_arch = "wasm32")]
+    start: WFCGrid,
+}
+
+impl Generation {
+    pub fn collapsed(&self) -> usize {
+        self.shared.collapsed.load(Ordering::Relaxed)
+    }
+
+    pub fn cancel(&self) {
+        self.shared.cancel.store(true, Ordering::Relaxed);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.shared.cancel.load(Ordering::Relaxed)
+    }
+}
+
+/// Collapses up to `budget` cells, publishing each finished cell.
+/// Returns `Ok(true)` once the grid is fully collapsed.
+fn run_steps(grid: &mut WFCGrid, shared: &Shared, budget: usize) -> Result<bool, WFCError> {
+    for _ in 0..budget {
+        if shared.cancel.load(Ordering::Relaxed) {
+            return Ok(true);
+        }
+        let Some((x, y)) = grid.collapse_next()? else {
+            return Ok(true);
+        };
+        grid.propagate(x, y)?;
+
+        if let Some(tile_type) = grid.tile_at(x, y) {
+            shared
+                .finished
+                .lock()
+                .unwrap()
+                .cells
+                .push((x, y, tile_type));
+        }
+        shared.collapsed.fetch_add(1, Ordering::Relaxed);
+    }
+    Ok(false)
+}
+
+/// Starts generating every undecided cell of the current grid, keeping the tiles already placed.
+/// After a contradiction the generation starts over, up to `MAX_ATTEMPTS` times.
+pub fn start_generation(commands: &mut Commands, wfc_state: &WFCState) {
+    let grid = wfc_state.grid.clone();
+    let total = grid.cells.iter().filter(|c| !c.collapsed).count();
+    let shared = Arc::new(Shared::default());
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let generation = {
+        let worker = shared.clone();
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            loop {
+                let mut attempt = grid.clone();
+                match run_steps(&mut attempt, &worker, usize::MAX) {
+                    Ok(_) => return Ok(attempt),
+                    Err(err) if worker.restart(&err) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+        });
+        Generation {
+            total,
+            shared,
+            spawned: Vec::new(),
+            attempt: 0,
+            task,
+        }
+    };
+
+    #[cfg(target_arch = "wasm32")]
+    let generation = Generation {
+        total,
+        shared,
+        spawned: Vec::new(),
+        attempt: 0,
+        grid: grid.clone(),
+        start: grid,
+    };
+
+    commands.insert_resource(generation);
+}
+
+// Removes the tiles spawned by a failed attempt
+fn remove_spawned(
+    commands: &mut Commands,
+    generation: &mut Generation,
+    tile_map: &mut TileMap,
+    tile_assets: &TileAssets,
+) {
+    for (x, z) in generation.spawned.drain(..) {
+        tile_map.set_tile(commands, tile_assets, x, z, TileType::EMPTY, 0);
+    }
+}
+
+/// Spawns the cells finished by the worker and installs the generated grid once done.
+/// Generated buildings are free, a whole map would cost far more than the starting funds.
+pub fn poll_generation(
+    mut commands: Commands,
+    mut generation: ResMut<Generation>,
+    mut wfc_state: ResMut<WFCState>,
+    mut tile_map: ResMut<TileMap>,
+    tile_assets: Res<TileAssets>,
+) {
+    #[cfg(not(target_arch = "wasm32"))]
+    let result = block_on(poll_once(&mut generation.task));
+
+    #[cfg(target_arch = "wasm32")]
+    let result = {
+        let generation = generation.as_mut();
+        match run_steps(&mut generation.grid, &generation.shared, WASM_STEP_BUDGET) {
+            Ok(false) => None,
+            Ok(true) => Some(Ok(generation.grid.clone())),
+            Err(err) if generation.shared.restart(&err) => {
+                generation.grid = generation.start.clone();
+                None
+            }
+            Err(err) => Some(Err(err)),
+        }
+    };
+
+    // Everything has to be spawned once the worker is done, the budget only applies while it runs
+    let spawn_budget = if result.is_some() {
+        usize::MAX
+    } else {
+        SPAWN_BUDGET
+    };
+    let (attempt, finished): (_, Vec<_>) = {
+        let mut finished = generation.shared.finished.lock().unwrap();
+        let count = finished.cells.len().min(spawn_budget);
+        (finished.attempt, finished.cells.drain(..count).collect())
+    };
+    if attempt != generation.attempt {
+        remove_spawned(&mut commands, &mut generation, &mut tile_map, &tile_assets);
+        generation.attempt = attempt;
+    }
+    for (x, z, tile_type) in finished {
+        tile_map.set_tile(&mut commands, &tile_assets, x, z, tile_type, 0);
+        generation.spawned.push((x, z));
+    }
+
+    match result {
+        None => {}
+        Some(Ok(grid)) => {
+            if generation.is_cancelled() {
+                info!(
+                    "Generation cancelled after {}/{} cells",
+                    generation.collapsed(),
+                    generation.total
+                );
+            }
+            wfc_state.grid = grid;
+            commands.remove_resource::<Generation>();
+        }
+        Some(Err(err)) => {
+            // The grid was left untouched, only the generated tiles have to go
+            error!("Generation failed: {}", err);
+            remove_spawned(&mut commands, &mut generation, &mut tile_map, &tile_assets);
+            commands.remove_resource::<Generation>();
+        }
+    }
+}
+
+pub fn generation_panel(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    generation: Option<Res<Generation>>,
+    wfc_state: Res<WFCState>,
+) {
+    egui::Window::new("Generation")
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(10.0, -10.0))
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| match generation {
+            Some(generation) => {
+                let collapsed = generation.collapsed();
+                let progress = collapsed as f32 / generation.total.max(1) as f32;
+                ui.add(
+                    egui::ProgressBar::new(progress)
+                        .desired_width(200.0)
+                        .text(format!("{} / {} cells", collapsed, generation.total)),
+                );
+                if ui.button("Cancel").clicked() {
+                    generation.cancel();
+                }
+            }
+            None => {
+                if ui
+                    .button("🎲 Generate map")
+                    .on_hover_text("Generated buildings are free, their upkeep is not")
+                    .clicked()
+                {
+                    start_generation(&mut commands, &wfc_state);
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::{Budget, STARTING_FUNDS};
+    use crate::tile_loader::TileCatalogue;
+    use crate::tilemap::CellChanged;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::tasks::TaskPool;
+
+    #[test]
+    fn test_generation_is_free() {
+        AsyncComputeTaskPool::get_or_init(TaskPool::new);
+        // The map and the grid cover the same cells
+        let size = 8;
+        let catalogue = TileCatalogue::builtin();
+        let mut app = App::new();
+        app.add_event::<CellChanged>()
+            .insert_resource(TileMap::new(size, size, &catalogue))
+            .insert_resource(WFCState {
+                grid: WFCGrid::new(size, size, &catalogue),
+            })
+            .insert_resource(Budget::default())
+            .insert_resource(TileAssets {
+                scenes: vec![Vec::new(); catalogue.len()],
+                models: Vec::new(),
+            })
+            .insert_resource(catalogue.clone())
+            .add_systems(
+                Update,
+                poll_generation.run_if(resource_exists::<Generation>),
+            );
+        app.world_mut()
+            .run_system_once(|mut commands: Commands, wfc_state: Res<WFCState>| {
+                start_generation(&mut commands, &wfc_state)
+            })
+            .unwrap();
+
+        for _ in 0..10_000 {
+            if !app.world().contains_resource::<Generation>() {
+                break;
+            }
+            app.update();
+        }
+
+        let tile_map = app.world().resource::<TileMap>();
+        let buildings = tile_map
+            .tiles
+            .iter()
+            .flatten()
+            .filter(|tile| tile.tile_type != TileType::EMPTY)
+            .count();
+        assert_eq!(buildings, size * size);
+        assert_eq!(app.world().resource::<Budget>().funds, STARTING_FUNDS);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::picking::HoveredCell;
+use crate::tile_loader::TileCatalogue;
+use crate::tilemap::{TileMap, TileType};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+/// Lines float just above the ground, under the placement highlights.
+const LINE_HEIGHT: f32 = 0.01;
+const LINE_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.15);
+const HOVER_COLOR: Color = Color::srgb(1.0, 0.85, 0.2);
+
+/// Helpers to line buildings up: grid lines (G) and the coordinates of the hovered cell (X).
+#[derive(Resource)]
+pub struct GridOverlay {
+    pub lines: bool,
+    pub coordinates: bool,
+}
+
+impl Default for GridOverlay {
+    fn default() -> Self {
+        Self {
+            lines: true,
+            coordinates: false,
+        }
+    }
+}
+
+pub fn toggle_grid_overlay(
+    input: Res<ButtonInput<KeyCode>>,
+    mut overlay: ResMut<GridOverlay>,
+    mut egui_contexts: EguiContexts,
+) {
+    if egui_contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    if input.just_pressed(KeyCode::KeyG) {
+        overlay.lines = !overlay.lines;
+    }
+    if input.just_pressed(KeyCode::KeyX) {
+        overlay.coordinates = !overlay.coordinates;
+    }
+}
+
+/// Draws the cell borders and an outline around the hovered cell.
+pub fn draw_grid_overlay(
+    mut gizmos: Gizmos,
+    overlay: Res<GridOverlay>,
+    hovered: Res<HoveredCell>,
+    tile_map: Res<TileMap>,
+) {
+    // Cells are centred on integer coordinates, borders are halfway between them
+    if overlay.lines {
+        let (width, height) = (tile_map.width as f32, tile_map.height as f32);
+        for x in 0..=tile_map.width {
+            let x = x as f32 - 0.5;
+            gizmos.line(
+                Vec3::new(x, LINE_HEIGHT, -0.5),
+                Vec3::new(x, LINE_HEIGHT, height - 0.5),
+                LINE_COLOR,
+            );
+        }
+        for z in 0..=tile_map.height {
+            let z = z as f32 - 0.5;
+            gizmos.line(
+                Vec3::new(-0.5, LINE_HEIGHT, z),
+                Vec3::new(width - 0.5, LINE_HEIGHT, z),
+                LINE_COLOR,
+            );
+        }
+    }
+
+    if let Some((x, z)) = hovered.0 {
+        gizmos.rect(
+            Isometry3d::new(
+                Vec3::new(x as f32, LINE_HEIGHT * 2.0, z as f32),
+                Quat::from_rotation_x(std::f32::consts::FRAC_PI_2),
+            ),
+            Vec2::ONE,
+            HOVER_COLOR,
+        );
+    }
+}
+
+/// Bar at the bottom of the screen with the coordinates of the hovered cell and its tile.
+pub fn status_bar(
+    mut contexts: EguiContexts,
+    overlay: Res<GridOverlay>,
+    hovered: Res<HoveredCell>,
+    tile_map: Res<TileMap>,
+    catalogue: Res<TileCatalogue>,
+) {
+    if !overlay.coordinates {
+        return;
+    }
+    egui::TopBottomPanel::bottom("status_bar").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| match hovered.0 {
+            Some((x, z)) => {
+                ui.monospace(format!("x {:>3}  z {:>3}", x, z));
+                let tile = &tile_map.tiles[z][x];
+                if tile.tile_type != TileType::EMPTY {
+                    let definition = catalogue.get(tile.tile_type);
+                    ui.separator();
+                    ui.label(format!(
+                        "{} {} at ({}, {})",
+                        definition.icon, definition.name, tile.anchor.x, tile.anchor.y
+                    ));
+                }
+            }
+            None => {
+                ui.monospace("x   -  z   -");
+            }
+        });
+    });
+}
@@ -1,57 +1,63 @@
-use crate::app_config::GameState;
-use crate::game::GamePause;
-use crate::tile_loader::TileAssets;
+use crate::app_config::{GameState, InGameState};
+use crate::budget::Budget;
+use crate::chunks::WorldMode;
+use crate::demand::{CityStats, demand_bars};
+use crate::generation::Generation;
+use crate::grid_overlay::GridOverlay;
+use crate::planning::PlanningView;
+use crate::tile_loader::{TileAssets, TileCatalogue};
 use crate::tilemap::{SelectedTile, TileMap, TileType};
 use crate::undo_redo::UndoRedo;
+use crate::wfc::WFCState;
 use bevy::prelude::*;
 use bevy_egui::{EguiContexts, egui};
 
+/// Tiles offered in the building panel, filled from the tile catalogue by `load_tiles`.
 #[derive(Resource)]
 pub struct AvailableTiles {
     pub tiles: Vec<TileType>,
 }
 
-impl Default for AvailableTiles {
-    fn default() -> Self {
-        Self {
-            tiles: vec![
-                TileType::Residential,
-                TileType::Commercial,
-                TileType::Industrial,
-                TileType::Road,
-                TileType::Park,
-            ],
-        }
-    }
-}
-
-fn tile_icon(tile: &TileType) -> &'static str {
-    match tile {
-        TileType::Residential => "🏠",
-        TileType::Commercial => "🏢",
-        TileType::Industrial => "🏭",
-        TileType::Road => "🚏",
-        TileType::Park => "🌳",
-        _ => "❓",
-    }
-}
-
 pub fn game_menu(
     mut contexts: EguiContexts,
     mut next_state: ResMut<NextState<GameState>>,
-    mut pause: ResMut<GamePause>,
+    mut next_overlay: ResMut<NextState<InGameState>>,
+    mut planning: ResMut<PlanningView>,
+    mut overlay: ResMut<GridOverlay>,
+    world_mode: Res<WorldMode>,
 ) {
     egui::Window::new("Menu")
         .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-10.0, 10.0))
         .show(contexts.ctx_mut(), |ui| {
-            if ui
-                .button(if pause.paused { "Resume" } else { "Pause" })
-                .clicked()
-            {
-                pause.paused = !pause.paused;
+            if ui.button("Pause").on_hover_text("Esc").clicked() {
+                next_overlay.set(InGameState::Paused);
+            }
+            // The planning view and the grid overlay only cover the finite map
+            if *world_mode == WorldMode::Finite {
+                if ui
+                    .selectable_label(planning.enabled, "Planning view")
+                    .on_hover_text("P")
+                    .clicked()
+                {
+                    planning.enabled = !planning.enabled;
+                }
+                if ui
+                    .selectable_label(overlay.lines, "Grid lines")
+                    .on_hover_text("G")
+                    .clicked()
+                {
+                    overlay.lines = !overlay.lines;
+                }
+                if ui
+                    .selectable_label(overlay.coordinates, "Coordinates")
+                    .on_hover_text("X")
+                    .clicked()
+                {
+                    overlay.coordinates = !overlay.coordinates;
+                }
             }
             if ui.button("Settings").clicked() {
-                next_state.set(GameState::Settings);
+                next_overlay.set(InGameState::Settings);
             }
             if ui.button("Main menu").clicked() {
                 next_state.set(GameState::MainMenu);
@@ -62,14 +68,20 @@ pub fn game_menu(
         });
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn tile_panel(
     mut contexts: EguiContexts,
     tiles: Res<AvailableTiles>,
     mut selected_tile: ResMut<SelectedTile>,
     mut undo_redo: ResMut<UndoRedo>,
     mut tilemap: ResMut<TileMap>,
+    mut wfc_state: ResMut<WFCState>,
     mut commands: Commands,
+    catalogue: Res<TileCatalogue>,
     tile_assets: Res<TileAssets>,
+    mut budget: ResMut<Budget>,
+    city: Res<CityStats>,
+    generation: Option<Res<Generation>>,
 ) {
     egui::Window::new("Building Panel")
         .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -10.0))
@@ -78,18 +90,29 @@ pub fn tile_panel(
             ui.horizontal(|ui| {
                 for tile in &tiles.tiles {
                     let selected = *tile == selected_tile.0;
+                    let definition = catalogue.get(*tile);
                     if ui
-                        .selectable_label(selected, format!("{}\n{:?}", tile_icon(tile), tile))
+                        .selectable_label(
+                            selected,
+                            format!("{}\n{}", definition.icon, definition.name),
+                        )
+                        .on_hover_text(format!(
+                            "{:?}\nCost: {} $\nUpkeep: {} $ per month",
+                            definition.category, definition.cost, definition.upkeep
+                        ))
                         .clicked()
                     {
                         // Toggle selection
                         if selected_tile.0 == *tile {
-                            selected_tile.0 = TileType::Empty;
+                            selected_tile.0 = TileType::EMPTY;
                         } else {
                             selected_tile.0 = *tile;
                         }
                     }
                 }
+
+                ui.separator();
+                demand_bars(ui, &city);
             });
 
             ui.separator();
@@ -97,13 +120,30 @@ pub fn tile_panel(
             ui.vertical_centered(|ui| {
                 ui.set_max_width(150.0);
 
-                ui.horizontal(|ui| {
-                    if ui.button("↩️Undo").clicked() {
-                        undo_redo.undo(&mut tilemap, &mut commands);
-                    }
-                    if ui.button("↪️Redo").clicked() {
-                        undo_redo.redo(&mut tilemap, &mut commands, &tile_assets);
-                    }
+                // The generation installs its grid once done, the map must not change meanwhile
+                ui.add_enabled_ui(generation.is_none(), |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.button("↩️Undo").clicked() {
+                            undo_redo.undo(
+                                &mut tilemap,
+                                &mut wfc_state,
+                                &mut commands,
+                                &catalogue,
+                                &tile_assets,
+                                &mut budget,
+                            );
+                        }
+                        if ui.button("↪️Redo").clicked() {
+                            undo_redo.redo(
+                                &mut tilemap,
+                                &mut wfc_state,
+                                &mut commands,
+                                &catalogue,
+                                &tile_assets,
+                                &mut budget,
+                            );
+                        }
+                    });
                 });
             });
         });
@@ -0,0 +1,262 @@
+use crate::budget::Budget;
+use crate::picking::SelectedBuilding;
+use crate::tile_loader::{TileAssets, TileCatalogue};
+use crate::tilemap::{TileMap, TileType, demolish_tile, replace_tile};
+use crate::undo_redo::{Action, UndoRedo};
+use crate::wfc::{EAST, NORTH, SOUTH, WEST, WFCState, direction_name};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+/// Choices made in the inspector, kept between frames.
+#[derive(Default)]
+pub struct InspectorState {
+    building: Option<(usize, usize)>,
+    replacement: Option<TileType>,
+    /// Why the last action failed.
+    error: Option<String>,
+}
+
+/// Buildings touching the footprint anchored at (x, z), by direction.
+fn neighbours(
+    tile_map: &TileMap,
+    catalogue: &TileCatalogue,
+    x: usize,
+    z: usize,
+) -> [(usize, Vec<TileType>); 4] {
+    let tile_type = tile_map.tiles[z][x].tile_type;
+    let footprint: Vec<_> = catalogue.get(tile_type).footprint_cells(x, z).collect();
+    let mut result = [NORTH, SOUTH, EAST, WEST].map(|dir| (dir, Vec::new()));
+    let mut seen = Vec::new();
+
+    for &(cx, cz) in &footprint {
+        for (dir, found) in result.iter_mut() {
+            let offset = match *dir {
+                NORTH => IVec2::NEG_Y,
+                SOUTH => IVec2::Y,
+                EAST => IVec2::X,
+                _ => IVec2::NEG_X,
+            };
+            let cell = IVec2::new(cx as i32, cz as i32) + offset;
+            if cell.x < 0
+                || cell.y < 0
+                || cell.x as usize >= tile_map.width
+                || cell.y as usize >= tile_map.height
+                || footprint.contains(&(cell.x as usize, cell.y as usize))
+            {
+                continue;
+            }
+            let tile = &tile_map.tiles[cell.y as usize][cell.x as usize];
+            // A large building touching several cells is listed once
+            if tile.tile_type != TileType::EMPTY && !seen.contains(&(*dir, tile.anchor)) {
+                seen.push((*dir, tile.anchor));
+                found.push(tile.tile_type);
+            }
+        }
+    }
+    result
+}
+
+/// Details of the selected building, with actions to demolish, replace or rotate it.
+#[allow(clippy::too_many_arguments)]
+pub fn tile_inspector(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    mut selected_building: ResMut<SelectedBuilding>,
+    mut tile_map: ResMut<TileMap>,
+    mut wfc_state: ResMut<WFCState>,
+    mut undo_redo: ResMut<UndoRedo>,
+    mut budget: ResMut<Budget>,
+    catalogue: Res<TileCatalogue>,
+    tile_assets: Res<TileAssets>,
+    mut state: Local<InspectorState>,
+) {
+    if state.building != selected_building.0 {
+        *state = InspectorState {
+            building: selected_building.0,
+            ..default()
+        };
+    }
+    let Some((x, z)) = selected_building.0 else {
+        return;
+    };
+    let tile = &tile_map.tiles[z][x];
+    let tile_type = tile.tile_type;
+    if tile_type == TileType::EMPTY {
+        return;
+    }
+    let definition = catalogue.get(tile_type);
+    let size = definition.footprint();
+    let position = tile.position;
+    let rotation = tile.rotation;
+
+    let mut open = true;
+    let mut demolish = false;
+    let mut rotate = false;
+    let mut replace = None;
+
+    egui::Window::new("Inspector")
+        .anchor(egui::Align2::RIGHT_CENTER, egui::vec2(-10.0, 0.0))
+        .resizable(false)
+        .open(&mut open)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.heading(format!("{} {}", definition.icon, definition.name));
+
+            egui::Grid::new("inspector_info").show(ui, |ui| {
+                ui.label("Type");
+                ui.label(format!("{} (#{})", definition.id, tile_type.index()));
+                ui.end_row();
+                ui.label("Position");
+                ui.label(format!(
+                    "({}, {}), {}×{}",
+                    position.x, position.y, size.x, size.y
+                ));
+                ui.end_row();
+                ui.label("Rotation");
+                ui.label(format!("{}°", rotation as u32 * 90));
+                ui.end_row();
+                ui.label("Placed");
+                ui.label(match undo_redo.placed_at(x, z, tile_type) {
+                    Some(index) => format!("Action {} of {}", index + 1, undo_redo.history.len()),
+                    None => "By the map generator".to_string(),
+                });
+                ui.end_row();
+            });
+
+            ui.collapsing("Stats", |ui| {
+                egui::Grid::new("inspector_stats").show(ui, |ui| {
+                    ui.label("Category");
+                    ui.label(format!("{:?}", definition.category));
+                    ui.end_row();
+                    ui.label("Cost");
+                    ui.label(format!("{} $", definition.cost));
+                    ui.end_row();
+                    ui.label("Upkeep");
+                    ui.label(format!("{} $ per month", definition.upkeep));
+                    ui.end_row();
+                });
+            });
+
+            ui.collapsing("Neighbours", |ui| {
+                for (dir, tiles) in neighbours(&tile_map, &catalogue, x, z) {
+                    let names = if tiles.is_empty() {
+                        "nothing".to_string()
+                    } else {
+                        tiles
+                            .iter()
+                            .map(|t| catalogue.name(*t))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    };
+                    ui.label(format!("{}: {}", direction_name(dir), names));
+                }
+            });
+
+            ui.collapsing("Rules", |ui| {
+                let forbidden: Vec<_> = catalogue
+                    .placeable()
+                    .filter(|t| !catalogue.always_compatible(tile_type.index(), t.index()))
+                    .collect();
+                if forbidden.is_empty() {
+                    ui.label("Can go next to any tile");
+                }
+                for other in forbidden {
+                    let (a, b) = (tile_type.index(), other.index());
+                    // Sides forbidden for the same reason share a line
+                    let mut reasons: Vec<(&str, Vec<&str>)> = Vec::new();
+                    for dir in [NORTH, SOUTH, EAST, WEST] {
+                        if catalogue.compatible(dir, a, b) {
+                            continue;
+                        }
+                        let reason = catalogue.reason(dir, a, b);
+                        match reasons.iter_mut().find(|(r, _)| *r == reason) {
+                            Some((_, sides)) => sides.push(direction_name(dir)),
+                            None => reasons.push((reason, vec![direction_name(dir)])),
+                        }
+                    }
+                    for (reason, sides) in reasons {
+                        let on = if sides.len() == 4 {
+                            String::new()
+                        } else {
+                            format!(" on the {} side", sides.join(", "))
+                        };
+                        ui.label(format!(
+                            "Not next to {}{}: {}",
+                            catalogue.name(other),
+                            on,
+                            reason
+                        ));
+                    }
+                }
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                demolish = ui.button("🗑 Demolish").clicked();
+                rotate = ui
+                    .add_enabled(definition.rotates(), egui::Button::new("⟳ Rotate"))
+                    .on_disabled_hover_text("Only square buildings can be rotated")
+                    .clicked();
+            });
+            ui.horizontal(|ui| {
+                let choice = state.replacement.filter(|t| *t != tile_type);
+                egui::ComboBox::from_id_salt("inspector_replacement")
+                    .selected_text(choice.map_or("Replace with…", |t| catalogue.name(t)))
+                    .show_ui(ui, |ui| {
+                        for other in catalogue.placeable().filter(|t| *t != tile_type) {
+                            ui.selectable_value(
+                                &mut state.replacement,
+                                Some(other),
+                                format!("{} {}", catalogue.get(other).icon, catalogue.name(other)),
+                            );
+                        }
+                    });
+                let shortfall = choice.and_then(|t| budget.shortfall(catalogue.get(t)));
+                let mut button = ui.add_enabled(
+                    choice.is_some() && shortfall.is_none(),
+                    egui::Button::new("Replace"),
+                );
+                if let Some(shortfall) = shortfall {
+                    button = button.on_disabled_hover_text(shortfall);
+                }
+                if button.clicked() {
+                    replace = choice;
+                }
+            });
+            if let Some(error) = &state.error {
+                ui.colored_label(egui::Color32::LIGHT_RED, error);
+            }
+        });
+
+    if !open {
+        selected_building.0 = None;
+    } else if demolish {
+        demolish_tile(
+            &mut commands,
+            &mut tile_map,
+            &mut wfc_state,
+            &mut undo_redo,
+            x,
+            z,
+        );
+        selected_building.0 = None;
+    } else if rotate {
+        tile_map.set_rotation(&mut commands, &tile_assets, x, z, rotation + 1);
+        undo_redo.add_action(Action::RotateTile(x, z));
+        state.error = None;
+    } else if let Some(new_type) = replace {
+        state.error = replace_tile(
+            &mut commands,
+            &mut tile_map,
+            &mut wfc_state,
+            &catalogue,
+            &tile_assets,
+            &mut undo_redo,
+            &mut budget,
+            x,
+            z,
+            new_type,
+        )
+        .err()
+        .map(|err| format!("Cannot replace with {}: {}", catalogue.name(new_type), err));
+    }
+}
@@ -1,25 +1,57 @@
 mod app_config;
+mod benchmark;
+mod blueprint;
+mod budget;
+mod chunks;
+mod demand;
 mod game;
+mod generation;
+mod grid_overlay;
 mod ingame_ui;
+mod inspector;
+mod minimap;
+mod pause;
+mod picking;
+mod planning;
+mod save;
+mod simulation;
 mod tile_loader;
 mod tilemap;
 mod ui;
 mod undo_redo;
 mod wfc;
+mod wfc_debug;
 
+use bevy::asset::io::AssetSourceBuilder;
+use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
 use bevy::prelude::*;
 use bevy_egui::EguiPlugin;
 
 use crate::undo_redo::UndoRedo;
-use app_config::{GameSettings, GameState};
-use game::GamePause;
-use ingame_ui::AvailableTiles;
-use tile_loader::load_tiles;
-use tilemap::{SelectedTile, TileType, setup_grid};
-use wfc::WFCState;
+use app_config::{GameSettings, GameState, InGameState};
+use benchmark::Benchmark;
+use blueprint::BlueprintTool;
+use budget::Budget;
+use chunks::{InfiniteWorld, WorldMode, is_infinite};
+use demand::CityStats;
+use generation::Generation;
+use grid_overlay::GridOverlay;
+use minimap::Minimap;
+use picking::{HoveredCell, SelectedBuilding};
+use planning::PlanningView;
+use save::LoadMap;
+use simulation::{SimulationClock, SimulationSpeed, SimulationTick};
+use tile_loader::{TileLog, load_tiles};
+use tilemap::{CellChanged, PlacementRefusal, SelectedTile, TileType, setup_grid};
+use wfc_debug::WFCDebug;
 
 fn main() {
     App::new()
+        // Must be registered before the AssetPlugin
+        .register_asset_source(
+            tile_loader::PACKS_SOURCE,
+            AssetSourceBuilder::platform_default(tile_loader::PACKS_DIR, None),
+        )
         .add_plugins(DefaultPlugins.set(AssetPlugin {
             watch_for_changes_override: Some(true),
             ..default()
@@ -27,36 +59,207 @@ fn main() {
         .add_plugins(EguiPlugin {
             enable_multipass_for_primary_context: false,
         })
+        .add_plugins(FrameTimeDiagnosticsPlugin::default())
+        .add_plugins(MeshPickingPlugin)
+        // Only the ground and placed buildings are pickable, previews and overlays are not
+        .insert_resource(MeshPickingSettings {
+            require_markers: true,
+            ..default()
+        })
         .insert_resource(GameSettings::default())
-        .insert_resource(GamePause::default())
-        .insert_resource(AvailableTiles::default())
-        .insert_resource(SelectedTile(TileType::Empty))
+        .insert_resource(SelectedTile(TileType::EMPTY))
         .insert_resource(UndoRedo::default())
-        .insert_resource(WFCState::default())
+        .insert_resource(WFCDebug::default())
+        .insert_resource(WorldMode::default())
+        .insert_resource(InfiniteWorld::default())
+        .insert_resource(Benchmark::default())
+        .insert_resource(HoveredCell::default())
+        .insert_resource(SelectedBuilding::default())
+        .insert_resource(PlacementRefusal::default())
+        .insert_resource(BlueprintTool::default())
+        .insert_resource(PlanningView::default())
+        .insert_resource(GridOverlay::default())
+        .insert_resource(Minimap::default())
+        .insert_resource(SimulationClock::default())
+        .insert_resource(Budget::default())
+        .insert_resource(CityStats::default())
+        .insert_resource(SimulationSpeed::default())
+        .insert_resource(Time::<Fixed>::from_seconds(simulation::TICK_LENGTH))
+        .add_event::<CellChanged>()
         .init_state::<GameState>()
-        .add_systems(Startup, (load_tiles, setup_grid))
+        .add_sub_state::<InGameState>()
         .add_systems(
-            PostStartup,
-            (/*wfc::generate_level,*/app_config::play_background_music),
+            Startup,
+            (
+                (load_tiles, setup_grid, wfc_debug::setup_wfc_debug).chain(),
+                chunks::setup_chunks,
+                blueprint::load_blueprints,
+                save::load_save_file,
+            ),
         )
-        .add_systems(OnEnter(GameState::InGame), game::setup_game)
+        .add_systems(PostStartup, app_config::play_background_music)
+        .add_observer(picking::on_pointer_move)
+        .add_observer(picking::on_pointer_out)
+        .add_observer(picking::on_click)
+        .add_systems(
+            OnEnter(GameState::InGame),
+            (
+                game::setup_game,
+                chunks::apply_world_mode,
+                save::load_map.run_if(resource_exists::<LoadMap>),
+            ),
+        )
+        .configure_sets(
+            FixedUpdate,
+            SimulationTick
+                .after(simulation::advance_clock)
+                .run_if(in_state(GameState::InGame)),
+        )
+        .add_systems(
+            FixedUpdate,
+            simulation::advance_clock.run_if(in_state(GameState::InGame)),
+        )
+        .add_systems(
+            FixedUpdate,
+            (budget::settle_budget, demand::update_city)
+                .in_set(SimulationTick)
+                .run_if(not(is_infinite)),
+        )
+        .add_systems(OnEnter(InGameState::Playing), pause::resume_time)
+        .add_systems(OnEnter(InGameState::Paused), pause::pause_time)
+        .add_systems(OnEnter(InGameState::Settings), pause::pause_time)
+        .add_systems(
+            OnExit(GameState::InGame),
+            (
+                pause::resume_time,
+                simulation::reset_simulation,
+                game::end_game,
+                chunks::unload_world,
+            ),
+        )
+        .add_systems(OnEnter(GameState::Benchmark), benchmark::setup_benchmark)
+        .add_systems(OnExit(GameState::Benchmark), benchmark::cleanup_benchmark)
         .add_systems(
             Update,
             (
+                // Loading
+                ui::loading_screen.run_if(in_state(GameState::Loading)),
+                tile_loader::check_tile_assets.run_if(in_state(GameState::Loading)),
                 // UI Menus
                 ui::main_menu.run_if(in_state(GameState::MainMenu)),
                 ui::settings_menu.run_if(in_state(GameState::Settings)),
                 ui::load_game_menu.run_if(in_state(GameState::LoadGame)),
+                ui::ingame_settings.run_if(in_state(InGameState::Settings)),
+                pause::pause_menu.run_if(in_state(InGameState::Paused)),
+                pause::toggle_pause
+                    .before(blueprint::blueprint_input)
+                    .run_if(in_state(InGameState::Playing)),
                 // In-game systems
-                game::camera_movement.run_if(in_state(GameState::InGame)),
+                game::camera_movement
+                    .run_if(in_state(InGameState::Playing).or(in_state(GameState::Benchmark))),
+                game::camera_bookmarks
+                    .run_if(in_state(InGameState::Playing))
+                    .run_if(not(is_infinite)),
                 ingame_ui::game_menu.run_if(in_state(GameState::InGame)),
-                ingame_ui::tile_panel.run_if(in_state(GameState::InGame)),
-                tilemap::place_tile_preview.run_if(in_state(GameState::InGame)),
+                ingame_ui::tile_panel
+                    .run_if(in_state(GameState::InGame))
+                    .run_if(not(is_infinite)),
+                tilemap::place_tile_preview
+                    .run_if(in_state(InGameState::Playing))
+                    .run_if(not(is_infinite))
+                    .run_if(not(resource_exists::<Generation>)),
+                (
+                    picking::make_tiles_pickable,
+                    picking::refresh_hovered_cell.run_if(in_state(InGameState::Playing)),
+                    picking::update_selection_marker,
+                    inspector::tile_inspector
+                        .run_if(in_state(InGameState::Playing))
+                        .run_if(not(resource_exists::<Generation>)),
+                    minimap::minimap_window,
+                    tilemap::show_placement_refusal.run_if(in_state(InGameState::Playing)),
+                )
+                    .run_if(in_state(GameState::InGame))
+                    .run_if(not(is_infinite)),
                 tilemap::update_placement_highlights
                     .after(tilemap::place_tile_preview)
-                    .run_if(in_state(GameState::InGame)),
+                    .run_if(in_state(GameState::InGame))
+                    .run_if(not(is_infinite)),
+                chunks::update_chunks
+                    .run_if(in_state(GameState::InGame))
+                    .run_if(is_infinite),
+                benchmark::benchmark_panel.run_if(in_state(GameState::Benchmark)),
                 ui::update_volume,
+                ui::tile_log_window.run_if(resource_exists::<TileLog>),
             ),
         )
+        .add_systems(
+            Update,
+            (
+                wfc_debug::toggle_wfc_debug,
+                wfc_debug::step_wfc_debug
+                    .run_if(in_state(InGameState::Playing))
+                    .run_if(not(resource_exists::<Generation>)),
+                wfc_debug::update_domain_overlay,
+                wfc_debug::wfc_debug_panel,
+                wfc_debug::draw_domain_numbers,
+            )
+                .chain()
+                .run_if(in_state(GameState::InGame))
+                .run_if(not(is_infinite)),
+        )
+        .add_systems(
+            Update,
+            (
+                planning::toggle_planning_view.run_if(in_state(InGameState::Playing)),
+                planning::update_planning_camera.before(game::camera_movement),
+                planning::update_planning_markers,
+                planning::draw_planning_icons,
+            )
+                .run_if(in_state(GameState::InGame))
+                .run_if(not(is_infinite)),
+        )
+        .add_systems(
+            Update,
+            (
+                simulation::apply_simulation_speed,
+                simulation::clock_panel,
+                budget::budget_panel.run_if(not(is_infinite)),
+            )
+                .run_if(in_state(GameState::InGame)),
+        )
+        .add_systems(
+            Update,
+            (
+                grid_overlay::toggle_grid_overlay.run_if(in_state(InGameState::Playing)),
+                grid_overlay::draw_grid_overlay,
+                // Before the anchored windows so they make room for it
+                grid_overlay::status_bar
+                    .before(ingame_ui::tile_panel)
+                    .before(minimap::minimap_window),
+            )
+                .run_if(in_state(GameState::InGame))
+                .run_if(not(is_infinite)),
+        )
+        .add_systems(
+            Update,
+            (
+                blueprint::blueprint_panel,
+                blueprint::blueprint_input,
+                blueprint::blueprint_preview,
+            )
+                .chain()
+                .run_if(in_state(InGameState::Playing))
+                .run_if(not(is_infinite))
+                .run_if(not(resource_exists::<Generation>)),
+        )
+        .add_systems(
+            Update,
+            (
+                generation::generation_panel,
+                generation::poll_generation.run_if(resource_exists::<Generation>),
+            )
+                .run_if(in_state(GameState::InGame))
+                .run_if(not(is_infinite)),
+        )
         .run();
 }
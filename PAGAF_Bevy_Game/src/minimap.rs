@@ -0,0 +1,171 @@
+use crate::game::CameraRig;
+use crate::tile_loader::TileCatalogue;
+use crate::tilemap::{CellChanged, TileMap, TileType};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+/// Size of a map cell on the minimap, in logical pixels.
+const CELL_SIZE: f32 = 3.0;
+/// How far the frustum outline goes when a screen corner looks above the horizon.
+const MAX_VIEW_DISTANCE: f32 = 200.0;
+
+/// One pixel per cell, kept on the CPU so only changed cells are uploaded.
+/// Reset by `end_game`, the next game rebuilds it from its own map.
+#[derive(Resource, Default)]
+pub struct Minimap {
+    image: Option<egui::ColorImage>,
+    texture: Option<egui::TextureHandle>,
+    /// Indexed by `TileType::index`.
+    colours: Vec<egui::Color32>,
+}
+
+/// Tiles of a category get its colour, a bit darker for each other tile of the category.
+fn tile_colours(catalogue: &TileCatalogue) -> Vec<egui::Color32> {
+    let tiles = catalogue.tiles();
+    tiles
+        .iter()
+        .enumerate()
+        .map(|(i, tile)| {
+            if i == TileType::EMPTY.index() {
+                return egui::Color32::from_gray(20);
+            }
+            let rank = tiles[1..i]
+                .iter()
+                .filter(|t| t.category == tile.category)
+                .count();
+            let shade = 1.0 - 0.15 * (rank % 4) as f32;
+            let [r, g, b, _] = tile.category.color().to_srgba().to_f32_array();
+            egui::Color32::from_rgb(
+                (r * shade * 255.0) as u8,
+                (g * shade * 255.0) as u8,
+                (b * shade * 255.0) as u8,
+            )
+        })
+        .collect()
+}
+
+/// Ground points seen at the corners of the screen.
+fn frustum_footprint(camera: &Camera, camera_transform: &GlobalTransform) -> Option<[Vec2; 4]> {
+    let size = camera.logical_viewport_size()?;
+    let corners = [
+        Vec2::ZERO,
+        Vec2::new(size.x, 0.0),
+        size,
+        Vec2::new(0.0, size.y),
+    ];
+    let mut footprint = [Vec2::ZERO; 4];
+    for (point, corner) in footprint.iter_mut().zip(corners) {
+        let ray = camera.viewport_to_world(camera_transform, corner).ok()?;
+        let distance = ray
+            .intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Y))
+            .unwrap_or(MAX_VIEW_DISTANCE)
+            .min(MAX_VIEW_DISTANCE);
+        *point = ray.get_point(distance).xz();
+    }
+    Some(footprint)
+}
+
+/// Minimap of the `TileMap` with the area seen by the camera. Clicking or dragging on it
+/// moves the camera there.
+pub fn minimap_window(
+    mut contexts: EguiContexts,
+    tile_map: Res<TileMap>,
+    catalogue: Res<TileCatalogue>,
+    mut changes: EventReader<CellChanged>,
+    mut cameras: Query<(&Camera, &GlobalTransform, &mut CameraRig)>,
+    mut minimap: ResMut<Minimap>,
+) {
+    let ctx = contexts.ctx_mut().clone();
+    let (width, height) = (tile_map.width, tile_map.height);
+
+    if minimap.texture.is_none() {
+        minimap.colours = tile_colours(&catalogue);
+        let pixels = tile_map
+            .tiles
+            .iter()
+            .flatten()
+            .map(|tile| minimap.colours[tile.tile_type.index()])
+            .collect();
+        let image = egui::ColorImage {
+            size: [width, height],
+            pixels,
+        };
+        minimap.texture =
+            Some(ctx.load_texture("minimap", image.clone(), egui::TextureOptions::NEAREST));
+        minimap.image = Some(image);
+        changes.clear();
+    }
+
+    // Only the rectangle around the changed cells is uploaded
+    let mut dirty: Option<(UVec2, UVec2)> = None;
+    for &CellChanged { x, z } in changes.read() {
+        let colour = minimap.colours[tile_map.tiles[z][x].tile_type.index()];
+        if let Some(image) = &mut minimap.image {
+            image.pixels[z * width + x] = colour;
+        }
+        let cell = UVec2::new(x as u32, z as u32);
+        dirty = Some(dirty.map_or((cell, cell), |(min, max)| (min.min(cell), max.max(cell))));
+    }
+    if let Some((min, max)) = dirty {
+        let size = max - min + UVec2::ONE;
+        let image = minimap.image.as_ref().unwrap();
+        let pixels = (min.y..=max.y)
+            .flat_map(|z| {
+                let row = z as usize * width;
+                image.pixels[row + min.x as usize..=row + max.x as usize].to_vec()
+            })
+            .collect();
+        let patch = egui::ColorImage {
+            size: [size.x as usize, size.y as usize],
+            pixels,
+        };
+        if let Some(texture) = &mut minimap.texture {
+            texture.set_partial(
+                [min.x as usize, min.y as usize],
+                patch,
+                egui::TextureOptions::NEAREST,
+            );
+        }
+    }
+
+    let Ok((camera, camera_transform, mut rig)) = cameras.single_mut() else {
+        return;
+    };
+    let footprint = frustum_footprint(camera, camera_transform);
+    let texture = minimap.texture.as_ref().unwrap();
+    let mut jump = None;
+
+    egui::Window::new("Minimap")
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+        .resizable(false)
+        .show(&ctx, |ui| {
+            let size = egui::vec2(width as f32, height as f32) * CELL_SIZE;
+            let response =
+                ui.add(egui::Image::new((texture.id(), size)).sense(egui::Sense::click_and_drag()));
+            let rect = response.rect;
+            // Cell centres are on integer coordinates
+            let to_screen = |p: Vec2| rect.min + egui::vec2(p.x + 0.5, p.y + 0.5) * CELL_SIZE;
+
+            if let Some(footprint) = footprint {
+                let points = footprint.iter().map(|p| to_screen(*p)).collect();
+                ui.painter()
+                    .with_clip_rect(rect)
+                    .add(egui::Shape::closed_line(
+                        points,
+                        egui::Stroke::new(1.5, egui::Color32::WHITE),
+                    ));
+            }
+
+            if (response.clicked() || response.dragged())
+                && let Some(pos) = response.interact_pointer_pos()
+            {
+                let cell = (pos - rect.min) / CELL_SIZE - egui::vec2(0.5, 0.5);
+                jump = Some(Vec2::new(cell.x, cell.y));
+            }
+        });
+
+    if let Some(target) = jump {
+        rig.flight = None;
+        rig.focus = Vec3::new(target.x, rig.focus.y, target.y);
+    }
+}
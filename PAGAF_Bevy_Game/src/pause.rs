@@ -0,0 +1,98 @@
+use crate::app_config::{GameState, InGameState};
+use crate::blueprint::BlueprintTool;
+use crate::budget::Budget;
+use crate::chunks::WorldMode;
+use crate::demand::CityStats;
+use crate::generation::Generation;
+use crate::save::SaveFile;
+use crate::simulation::SimulationClock;
+use crate::tile_loader::TileCatalogue;
+use crate::tilemap::TileMap;
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+/// Escape pauses the game, unless it is used to leave the blueprint tool.
+pub fn toggle_pause(
+    input: Res<ButtonInput<KeyCode>>,
+    blueprint_tool: Res<BlueprintTool>,
+    mut next_state: ResMut<NextState<InGameState>>,
+    mut egui_contexts: EguiContexts,
+) {
+    if input.just_pressed(KeyCode::Escape)
+        && !blueprint_tool.is_active()
+        && !egui_contexts.ctx_mut().wants_keyboard_input()
+    {
+        next_state.set(InGameState::Paused);
+    }
+}
+
+/// Virtual time, and everything driven by it, stops while the game is paused or in settings.
+pub fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+pub fn resume_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+/// Pause menu over the dimmed game. Escape or a click outside resumes.
+#[allow(clippy::too_many_arguments)]
+pub fn pause_menu(
+    mut contexts: EguiContexts,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_overlay: ResMut<NextState<InGameState>>,
+    mut save_file: ResMut<SaveFile>,
+    tile_map: Res<TileMap>,
+    catalogue: Res<TileCatalogue>,
+    budget: Res<Budget>,
+    clock: Res<SimulationClock>,
+    city: Res<CityStats>,
+    mode: Res<WorldMode>,
+    generation: Option<Res<Generation>>,
+    mut status: Local<Option<String>>,
+) {
+    let mut resume = false;
+    let mut save = false;
+    let modal = egui::Modal::new(egui::Id::new("pause_menu")).show(contexts.ctx_mut(), |ui| {
+        ui.set_width(200.0);
+        ui.vertical_centered_justified(|ui| {
+            ui.heading("Paused");
+            ui.add_space(10.0);
+            resume = ui.button("Resume").clicked();
+            let infinite = *mode == WorldMode::Infinite;
+            save = ui
+                .add_enabled(generation.is_none() && !infinite, egui::Button::new("Save"))
+                .on_disabled_hover_text(if infinite {
+                    "The infinite world cannot be saved, the saved city is kept"
+                } else {
+                    "Wait for the map generation to finish"
+                })
+                .clicked();
+            if ui.button("Settings").clicked() {
+                next_overlay.set(InGameState::Settings);
+                *status = None;
+            }
+            if ui.button("Main menu").clicked() {
+                next_state.set(GameState::MainMenu);
+                *status = None;
+            }
+            if let Some(status) = &*status {
+                ui.add_space(10.0);
+                ui.label(status);
+            }
+        });
+    });
+
+    if save {
+        *status = Some(
+            match save_file.save_map(*mode, &tile_map, &catalogue, &budget, &clock, &city) {
+                Ok(()) => "Game saved".to_string(),
+                Err(err) => err,
+            },
+        );
+    }
+    if resume || modal.should_close() {
+        *status = None;
+        next_overlay.set(InGameState::Playing);
+    }
+}
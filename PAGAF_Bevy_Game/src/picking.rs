@@ -0,0 +1,291 @@
+use crate::app_config::{GameState, InGameState};
+use crate::blueprint::BlueprintTool;
+use crate::budget::Budget;
+use crate::chunks::WorldMode;
+use crate::game::CameraRig;
+use crate::generation::Generation;
+use crate::tile_loader::{TileAssets, TileCatalogue};
+use crate::tilemap::{GridTile, PlacementRefusal, SelectedTile, TileMap, TileType, place_tile};
+use crate::undo_redo::UndoRedo;
+use crate::wfc::WFCState;
+use bevy::picking::mesh_picking::ray_cast::{MeshRayCast, MeshRayCastSettings};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+/// Grid cell under the pointer, on the ground or on a building.
+#[derive(Resource, Default)]
+pub struct HoveredCell(pub Option<(usize, usize)>);
+
+/// Anchor of the building clicked last, for inspection.
+#[derive(Resource, Default)]
+pub struct SelectedBuilding(pub Option<(usize, usize)>);
+
+/// Pickable entity standing for a building placed on the `TileMap`, at its anchor cell:
+/// the root of its model, or its marker in the planning view.
+#[derive(Component)]
+pub struct PlacedTile {
+    pub x: usize,
+    pub z: usize,
+}
+
+// Footprint outline of the selected building
+#[derive(Component)]
+pub struct SelectionMarker;
+
+/// Building meshes are spawned as children of the `PlacedTile`, they are made pickable here.
+pub fn make_tiles_pickable(mut commands: Commands, tiles: Query<&Children, Added<PlacedTile>>) {
+    for children in tiles.iter() {
+        for child in children.iter() {
+            commands.entity(child).insert(Pickable::default());
+        }
+    }
+}
+
+fn cell_at(tile_map: &TileMap, position: Vec3) -> Option<(usize, usize)> {
+    let x = position.x.round();
+    let z = position.z.round();
+    if x < 0.0 || z < 0.0 || x >= tile_map.width as f32 || z >= tile_map.height as f32 {
+        return None;
+    }
+    Some((x as usize, z as usize))
+}
+
+pub fn on_pointer_move(
+    mut trigger: Trigger<Pointer<Move>>,
+    tile_map: Res<TileMap>,
+    world_mode: Res<WorldMode>,
+    mut hovered: ResMut<HoveredCell>,
+    state: Option<Res<State<InGameState>>>,
+) {
+    trigger.propagate(false);
+    if !is_playing(state) || *world_mode == WorldMode::Infinite {
+        return;
+    }
+    if let Some(position) = trigger.hit.position {
+        hovered.0 = cell_at(&tile_map, position);
+    }
+}
+
+pub fn on_pointer_out(
+    mut trigger: Trigger<Pointer<Out>>,
+    mut hovered: ResMut<HoveredCell>,
+    state: Option<Res<State<InGameState>>>,
+) {
+    trigger.propagate(false);
+    if is_playing(state) {
+        hovered.0 = None;
+    }
+}
+
+/// The pointer observers only notice the pointer moving. When the camera moves under a
+/// still pointer, the hovered cell is found again by casting the cursor ray.
+#[allow(clippy::type_complexity)]
+pub fn refresh_hovered_cell(
+    cameras: Query<(&Camera, &GlobalTransform), (With<CameraRig>, Changed<GlobalTransform>)>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    pickables: Query<(), With<Pickable>>,
+    mut ray_cast: MeshRayCast,
+    tile_map: Res<TileMap>,
+    mut hovered: ResMut<HoveredCell>,
+) {
+    let Ok((camera, camera_transform)) = cameras.single() else {
+        return;
+    };
+    let Some(cursor) = windows.single().ok().and_then(Window::cursor_position) else {
+        return;
+    };
+    let Ok(ray) = camera.viewport_to_world(camera_transform, cursor) else {
+        return;
+    };
+    // Same entities as the pointer, which only picks entities marked `Pickable`
+    let filter = |entity| pickables.contains(entity);
+    let settings = MeshRayCastSettings::default().with_filter(&filter);
+    let cell = ray_cast
+        .cast_ray(ray, &settings)
+        .first()
+        .and_then(|(_, hit)| cell_at(&tile_map, hit.point));
+    if hovered.0 != cell {
+        hovered.0 = cell;
+    }
+}
+
+// Observers run whatever the state, the map ignores clicks while the game is paused
+fn is_playing(state: Option<Res<State<InGameState>>>) -> bool {
+    state.is_some_and(|state| *state.get() == InGameState::Playing)
+}
+
+/// Two clicks closer than this on the same cell are a double click, in seconds.
+const DOUBLE_CLICK_TIME: f32 = 0.3;
+
+// Flies the camera to the building on (x, z), or to the cell if it is empty
+fn fly_to_cell(
+    tile_map: &TileMap,
+    catalogue: &TileCatalogue,
+    rigs: &mut Query<&mut CameraRig>,
+    x: usize,
+    z: usize,
+) {
+    let tile = &tile_map.tiles[z][x];
+    let focus = if tile.tile_type == TileType::EMPTY {
+        Vec3::new(x as f32, 0.0, z as f32)
+    } else {
+        Vec3::new(tile.anchor.x as f32, 0.0, tile.anchor.y as f32)
+            + catalogue.get(tile.tile_type).footprint_centre()
+    };
+    if let Ok(mut rig) = rigs.single_mut() {
+        rig.fly_to(focus);
+    }
+}
+
+/// Places the selected tile when clicking the ground, selects a building when clicking it.
+/// Without a tile to place, double clicking flies the camera to the clicked cell or building.
+#[allow(clippy::too_many_arguments)]
+pub fn on_click(
+    mut trigger: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    mut tile_map: ResMut<TileMap>,
+    mut wfc_state: ResMut<WFCState>,
+    mut selected_tile: ResMut<SelectedTile>,
+    mut selected_building: ResMut<SelectedBuilding>,
+    mut undo_redo: ResMut<UndoRedo>,
+    mut budget: ResMut<Budget>,
+    // Observers take at most 16 parameters
+    (catalogue, tile_assets, mut refusal): (
+        Res<TileCatalogue>,
+        Res<TileAssets>,
+        ResMut<PlacementRefusal>,
+    ),
+    generation: Option<Res<Generation>>,
+    blueprint_tool: Res<BlueprintTool>,
+    buildings: Query<&PlacedTile>,
+    ground: Query<(), With<GridTile>>,
+    state: Option<Res<State<InGameState>>>,
+    time: Res<Time<Real>>,
+    mut rigs: Query<&mut CameraRig>,
+    mut last_click: Local<Option<(f32, (usize, usize))>>,
+) {
+    // Clicks drag a region or stamp a blueprint instead
+    if trigger.button != PointerButton::Primary || blueprint_tool.is_active() || !is_playing(state)
+    {
+        return;
+    }
+    let target = trigger.target();
+    let building = buildings.get(target).ok();
+    if building.is_none() && !ground.contains(target) {
+        return;
+    }
+    // Clicks on a building mesh bubble up to the building root
+    if building.is_some() {
+        trigger.propagate(false);
+    }
+
+    // While placing, clicks only build, so a double click never pays for a building
+    let cell = trigger.hit.position.and_then(|p| cell_at(&tile_map, p));
+    if selected_tile.0 == TileType::EMPTY
+        && let Some((x, z)) = cell
+    {
+        let now = time.elapsed_secs();
+        if let Some((at, last)) = *last_click
+            && last == (x, z)
+            && now - at < DOUBLE_CLICK_TIME
+        {
+            *last_click = None;
+            fly_to_cell(&tile_map, &catalogue, &mut rigs, x, z);
+            return;
+        }
+        *last_click = Some((now, (x, z)));
+    } else {
+        *last_click = None;
+    }
+
+    if let Some(building) = building {
+        if selected_tile.0 == TileType::EMPTY {
+            selected_building.0 = Some((building.x, building.z));
+        }
+        return;
+    }
+
+    selected_building.0 = None;
+    if selected_tile.0 == TileType::EMPTY || generation.is_some() {
+        return;
+    }
+    let Some((x, z)) = cell else {
+        return;
+    };
+
+    match place_tile(
+        &mut commands,
+        &mut tile_map,
+        &mut wfc_state,
+        &catalogue,
+        &tile_assets,
+        &selected_tile,
+        &mut undo_redo,
+        &mut budget,
+        x,
+        z,
+    ) {
+        Ok(()) => {
+            selected_tile.0 = TileType::EMPTY;
+            refusal.message = None;
+        }
+        Err(message) => {
+            *refusal = PlacementRefusal {
+                message: Some(message),
+                position: trigger.pointer_location.position,
+                since: time.elapsed_secs(),
+            };
+        }
+    }
+}
+
+/// Outlines the footprint of the selected building. Clears the selection once the building is gone.
+#[allow(clippy::too_many_arguments)]
+pub fn update_selection_marker(
+    mut commands: Commands,
+    mut selected_building: ResMut<SelectedBuilding>,
+    tile_map: Res<TileMap>,
+    catalogue: Res<TileCatalogue>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    markers: Query<Entity, With<SelectionMarker>>,
+    mut marker_assets: Local<Option<(Handle<Mesh>, Handle<StandardMaterial>)>>,
+) {
+    if !(selected_building.is_changed() || tile_map.is_changed()) {
+        return;
+    }
+
+    for entity in markers.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let Some((x, z)) = selected_building.0 else {
+        return;
+    };
+    let tile_type = tile_map.tiles[z][x].tile_type;
+    if tile_type == TileType::EMPTY {
+        selected_building.0 = None;
+        return;
+    }
+
+    let (mesh, material) = marker_assets.get_or_insert_with(|| {
+        (
+            meshes.add(Plane3d::default().mesh().size(1.0, 1.0)),
+            materials.add(StandardMaterial {
+                base_color: Color::srgba(1.0, 1.0, 1.0, 0.35),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            }),
+        )
+    });
+    for (cx, cz) in catalogue.get(tile_type).footprint_cells(x, z) {
+        commands.spawn((
+            Mesh3d(mesh.clone()),
+            MeshMaterial3d(material.clone()),
+            Transform::from_xyz(cx as f32, 0.06, cz as f32),
+            SelectionMarker,
+            StateScoped(GameState::InGame),
+        ));
+    }
+}
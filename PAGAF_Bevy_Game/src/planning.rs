@@ -0,0 +1,226 @@
+use crate::app_config::GameState;
+use crate::game::CameraRig;
+use crate::picking::PlacedTile;
+use crate::tile_loader::{TileCatalogue, TileCategory};
+use crate::tilemap::{TileMap, TileType};
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+use bevy_egui::{EguiContexts, egui};
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_2;
+
+/// Length of the switch between the perspective and the planning view, in seconds.
+const TRANSITION_TIME: f32 = 0.6;
+/// Markers float above the ground and the placement highlights.
+const MARKER_HEIGHT: f32 = 0.05;
+
+/// Orthographic top-down view, buildings are drawn as flat category colours with their icon.
+/// P toggles it.
+#[derive(Resource, Default)]
+pub struct PlanningView {
+    pub enabled: bool,
+    /// 0 in the perspective view, 1 in the planning view.
+    blend: f32,
+    /// Camera pitch to return to.
+    pitch: f32,
+}
+
+impl PlanningView {
+    /// Whether buildings are shown as markers, from halfway through the transition.
+    pub fn shows_markers(&self) -> bool {
+        self.blend >= 0.5
+    }
+}
+
+// Flat coloured quad over the footprint of a building
+#[derive(Component)]
+pub struct PlanningMarker;
+
+pub fn toggle_planning_view(
+    input: Res<ButtonInput<KeyCode>>,
+    mut planning: ResMut<PlanningView>,
+    mut egui_contexts: EguiContexts,
+) {
+    if input.just_pressed(KeyCode::KeyP) && !egui_contexts.ctx_mut().wants_keyboard_input() {
+        planning.enabled = !planning.enabled;
+    }
+}
+
+/// Tilts the camera down to the vertical then switches to an orthographic projection
+/// covering the same area, or the other way round.
+pub fn update_planning_camera(
+    mut planning: ResMut<PlanningView>,
+    mut cameras: Query<(&mut CameraRig, &mut Projection)>,
+    time: Res<Time<Real>>,
+) {
+    let Ok((mut rig, mut projection)) = cameras.single_mut() else {
+        return;
+    };
+
+    if planning.enabled && planning.blend == 0.0 {
+        planning.pitch = rig.pitch;
+    }
+    let step = time.delta_secs() / TRANSITION_TIME;
+    let blend = if planning.enabled {
+        (planning.blend + step).min(1.0)
+    } else {
+        (planning.blend - step).max(0.0)
+    };
+    if blend != planning.blend {
+        planning.blend = blend;
+    }
+
+    if blend > 0.0 || rig.top_down {
+        let t = blend * blend * (3.0 - 2.0 * blend);
+        let pitch = planning.pitch.lerp(FRAC_PI_2, t);
+        if rig.pitch != pitch {
+            rig.pitch = pitch;
+        }
+        if rig.top_down != (blend > 0.0) {
+            rig.top_down = blend > 0.0;
+        }
+    }
+
+    // Same visible height as the perspective camera at the focus
+    let fov = PerspectiveProjection::default().fov;
+    let height = 2.0 * rig.distance * (fov / 2.0).tan();
+    let orthographic = Projection::Orthographic(OrthographicProjection {
+        scaling_mode: ScalingMode::FixedVertical {
+            viewport_height: height,
+        },
+        ..OrthographicProjection::default_3d()
+    });
+    match (&*projection, blend == 1.0) {
+        (Projection::Perspective(_), true) => *projection = orthographic,
+        (Projection::Orthographic(ortho), true)
+            if !matches!(ortho.scaling_mode,
+                ScalingMode::FixedVertical { viewport_height } if viewport_height == height) =>
+        {
+            *projection = orthographic;
+        }
+        (Projection::Orthographic(_), false) => {
+            *projection = Projection::Perspective(PerspectiveProjection { fov, ..default() });
+        }
+        _ => {}
+    }
+}
+
+/// Swaps building models for pickable category markers while the planning view is shown.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn update_planning_markers(
+    mut commands: Commands,
+    planning: Res<PlanningView>,
+    tile_map: Res<TileMap>,
+    catalogue: Res<TileCatalogue>,
+    markers: Query<Entity, With<PlanningMarker>>,
+    mut models: Query<(&mut Visibility, Ref<PlacedTile>), Without<PlanningMarker>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut shown: Local<bool>,
+    mut marker_assets: Local<
+        Option<(
+            Handle<Mesh>,
+            HashMap<TileCategory, Handle<StandardMaterial>>,
+        )>,
+    >,
+) {
+    let show = planning.shows_markers();
+    let switched = show != *shown;
+    *shown = show;
+
+    // Models spawned while planning are hidden too
+    let visibility = if show {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    for (mut model, placed) in models.iter_mut() {
+        if switched || placed.is_added() {
+            model.set_if_neq(visibility);
+        }
+    }
+
+    if !(switched || (show && tile_map.is_changed())) {
+        return;
+    }
+    for entity in markers.iter() {
+        commands.entity(entity).despawn();
+    }
+    if !show {
+        return;
+    }
+
+    let (mesh, by_category) = marker_assets.get_or_insert_with(|| {
+        (
+            meshes.add(Plane3d::default().mesh().size(1.0, 1.0)),
+            HashMap::new(),
+        )
+    });
+    for (z, row) in tile_map.tiles.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            if tile.tile_type == TileType::EMPTY || tile.anchor != tile.position {
+                continue;
+            }
+            let definition = catalogue.get(tile.tile_type);
+            let category = definition.category;
+            let material = by_category.entry(category).or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: category.color(),
+                    unlit: true,
+                    ..default()
+                })
+            });
+            let size = definition.footprint().as_vec2() - Vec2::splat(0.1);
+            let centre =
+                Vec3::new(x as f32, MARKER_HEIGHT, z as f32) + definition.footprint_centre();
+            commands.spawn((
+                Mesh3d(mesh.clone()),
+                MeshMaterial3d(material.clone()),
+                Transform::from_translation(centre).with_scale(Vec3::new(size.x, 1.0, size.y)),
+                PlanningMarker,
+                PlacedTile { x, z },
+                Pickable::default(),
+                StateScoped(GameState::InGame),
+            ));
+        }
+    }
+}
+
+/// Tile icons over the planning markers.
+pub fn draw_planning_icons(
+    mut contexts: EguiContexts,
+    planning: Res<PlanningView>,
+    tile_map: Res<TileMap>,
+    catalogue: Res<TileCatalogue>,
+    camera: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+) {
+    if !planning.shows_markers() {
+        return;
+    }
+    let Ok((camera, camera_transform)) = camera.single() else {
+        return;
+    };
+
+    let painter = contexts
+        .ctx_mut()
+        .layer_painter(egui::LayerId::background());
+    for (z, row) in tile_map.tiles.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            if tile.tile_type == TileType::EMPTY || tile.anchor != tile.position {
+                continue;
+            }
+            let definition = catalogue.get(tile.tile_type);
+            let world =
+                Vec3::new(x as f32, MARKER_HEIGHT, z as f32) + definition.footprint_centre();
+            if let Ok(pos) = camera.world_to_viewport(camera_transform, world) {
+                painter.text(
+                    egui::pos2(pos.x, pos.y),
+                    egui::Align2::CENTER_CENTER,
+                    &definition.icon,
+                    egui::FontId::proportional(16.0),
+                    egui::Color32::WHITE,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::blueprint::{Blueprint, rebuild};
+use crate::budget::{Budget, STARTING_FUNDS, TaxRates};
+use crate::chunks::WorldMode;
+use crate::demand::CityStats;
+use crate::game::CameraView;
+use crate::simulation::SimulationClock;
+use crate::tile_loader::{TileAssets, TileCatalogue};
+use crate::tilemap::TileMap;
+use crate::wfc::WFCState;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Save file, next to the `tile_packs` folder.
+pub const SAVE_PATH: &str = "save.ron";
+
+/// What is kept between games. Read at startup, written whenever it changes.
+#[derive(Resource, Serialize, Deserialize)]
+pub struct SaveFile {
+    /// Camera views stored on F1–F4.
+    #[serde(default)]
+    pub bookmarks: [Option<CameraView>; 4],
+    /// Buildings of the finite map, stored from the pause menu.
+    #[serde(default)]
+    pub map: Option<Blueprint>,
+    /// Treasury, tax rates, date and population of the saved map.
+    #[serde(default = "starting_funds")]
+    pub funds: i64,
+    #[serde(default)]
+    pub tax_rates: TaxRates,
+    #[serde(default)]
+    pub tick: u64,
+    #[serde(default)]
+    pub population: f32,
+}
+
+fn starting_funds() -> i64 {
+    STARTING_FUNDS
+}
+
+impl Default for SaveFile {
+    fn default() -> Self {
+        Self {
+            bookmarks: Default::default(),
+            map: None,
+            funds: STARTING_FUNDS,
+            tax_rates: TaxRates::default(),
+            tick: 0,
+            population: 0.0,
+        }
+    }
+}
+
+impl SaveFile {
+    /// Writes the save file. The web build keeps it for the session only.
+    pub fn write(&self) -> Result<(), String> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let path = bevy::asset::io::file::FileAssetReader::get_base_path().join(SAVE_PATH);
+            let ron = ron::ser::to_string_pretty(self, Default::default())
+                .map_err(|e| format!("Cannot write {}: {}", SAVE_PATH, e))?;
+            std::fs::write(&path, ron).map_err(|e| format!("Cannot write {}: {}", SAVE_PATH, e))?;
+        }
+        Ok(())
+    }
+
+    /// Stores the buildings of the whole map and the state of the city, then writes the save file.
+    /// The infinite world is never stored in `tile_map`, saving it is refused to keep the saved city.
+    pub fn save_map(
+        &mut self,
+        mode: WorldMode,
+        tile_map: &TileMap,
+        catalogue: &TileCatalogue,
+        budget: &Budget,
+        clock: &SimulationClock,
+        city: &CityStats,
+    ) -> Result<(), String> {
+        if mode == WorldMode::Infinite {
+            return Err("The infinite world cannot be saved".to_string());
+        }
+        let max = UVec2::new(tile_map.width as u32, tile_map.height as u32) - UVec2::ONE;
+        self.map = Some(Blueprint::copy(tile_map, catalogue, UVec2::ZERO, max));
+        self.funds = budget.funds;
+        self.tax_rates = budget.tax_rates;
+        self.tick = clock.tick;
+        self.population = city.population;
+        self.write()
+    }
+}
+
+/// Asks for the saved map to replace the current one when entering the game.
+#[derive(Resource)]
+pub struct LoadMap;
+
+/// Rebuilds the saved map on the empty one, buildings that no longer fit the tile rules
+/// are left out.
+#[allow(clippy::too_many_arguments)]
+pub fn load_map(
+    mut commands: Commands,
+    save_file: Res<SaveFile>,
+    mut tile_map: ResMut<TileMap>,
+    mut wfc_state: ResMut<WFCState>,
+    mut budget: ResMut<Budget>,
+    mut clock: ResMut<SimulationClock>,
+    mut city: ResMut<CityStats>,
+    catalogue: Res<TileCatalogue>,
+    tile_assets: Res<TileAssets>,
+) {
+    commands.remove_resource::<LoadMap>();
+    let Some(map) = &save_file.map else {
+        return;
+    };
+
+    let buildings = map.transformed(&catalogue, 0, false);
+    let placed = rebuild(
+        &mut commands,
+        &mut tile_map,
+        &mut wfc_state,
+        &catalogue,
+        &tile_assets,
+        &buildings,
+    );
+    if placed < map.tiles.len() {
+        warn!(
+            "Loaded {} of the {} saved buildings",
+            placed,
+            map.tiles.len()
+        );
+    }
+    budget.funds = save_file.funds;
+    budget.tax_rates = save_file.tax_rates;
+    clock.tick = save_file.tick;
+    city.population = save_file.population;
+}
+
+pub fn load_save_file(mut commands: Commands) {
+    let mut save_file = SaveFile::default();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let path = bevy::asset::io::file::FileAssetReader::get_base_path().join(SAVE_PATH);
+        // No file yet is a fresh save
+        if let Ok(ron) = std::fs::read_to_string(&path) {
+            match ron::from_str(&ron) {
+                Ok(loaded) => save_file = loaded,
+                Err(e) => warn!("Cannot read {}: {}", SAVE_PATH, e),
+            }
+        }
+    }
+
+    commands.insert_resource(save_file);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infinite_world_keeps_the_save() {
+        let catalogue = TileCatalogue::builtin();
+        let saved = Blueprint {
+            name: "City".to_string(),
+            width: 1,
+            height: 1,
+            tiles: Vec::new(),
+        };
+        let mut save_file = SaveFile {
+            map: Some(saved),
+            funds: 1234,
+            ..default()
+        };
+
+        let result = save_file.save_map(
+            WorldMode::Infinite,
+            &TileMap::new(4, 4, &catalogue),
+            &catalogue,
+            &Budget::default(),
+            &SimulationClock::default(),
+            &CityStats::default(),
+        );
+        assert!(result.is_err());
+        assert_eq!(save_file.map.as_ref().unwrap().name, "City");
+        assert_eq!(save_file.funds, 1234);
+    }
+}
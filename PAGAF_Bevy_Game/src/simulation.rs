@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+/// Length of a simulation tick at 1× speed, in seconds. Used as the `FixedUpdate` timestep.
+pub const TICK_LENGTH: f64 = 0.25;
+pub const TICKS_PER_DAY: u64 = 8;
+const DAYS_PER_MONTH: u64 = 30;
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Time passed in the city, in ticks since the game started.
+#[derive(Resource, Default)]
+pub struct SimulationClock {
+    pub tick: u64,
+}
+
+impl SimulationClock {
+    /// Days since the game started.
+    pub fn day(&self) -> u64 {
+        self.tick / TICKS_PER_DAY
+    }
+
+    /// Whether the current tick is the first of a month.
+    pub fn is_new_month(&self) -> bool {
+        self.tick.is_multiple_of(TICKS_PER_DAY * DAYS_PER_MONTH)
+    }
+
+    /// Month of the year, from 0.
+    pub fn month(&self) -> usize {
+        (self.day() / DAYS_PER_MONTH % MONTHS.len() as u64) as usize
+    }
+
+    /// Year, from 1.
+    pub fn year(&self) -> u64 {
+        self.day() / (DAYS_PER_MONTH * MONTHS.len() as u64) + 1
+    }
+
+    pub fn date(&self) -> String {
+        format!(
+            "{} {}, year {}",
+            self.day() % DAYS_PER_MONTH + 1,
+            MONTHS[self.month()],
+            self.year()
+        )
+    }
+}
+
+/// How fast the simulation runs, set from the clock panel.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SimulationSpeed {
+    Paused,
+    #[default]
+    Normal,
+    Fast,
+    Fastest,
+}
+
+impl SimulationSpeed {
+    const ALL: [Self; 4] = [Self::Paused, Self::Normal, Self::Fast, Self::Fastest];
+
+    pub fn multiplier(self) -> f32 {
+        match self {
+            Self::Paused => 0.0,
+            Self::Normal => 1.0,
+            Self::Fast => 2.0,
+            Self::Fastest => 4.0,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Paused => "⏸",
+            Self::Normal => "1×",
+            Self::Fast => "2×",
+            Self::Fastest => "4×",
+        }
+    }
+}
+
+/// Simulation systems (economy, growth, traffic) run in `FixedUpdate` in this set,
+/// once per tick and after the clock advanced.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimulationTick;
+
+pub fn advance_clock(mut clock: ResMut<SimulationClock>) {
+    clock.tick += 1;
+}
+
+/// The speed scales `Time<Virtual>`, which drives `FixedUpdate`.
+/// Camera and UI animations follow `Time<Real>` and are not affected.
+pub fn apply_simulation_speed(speed: Res<SimulationSpeed>, mut time: ResMut<Time<Virtual>>) {
+    if speed.is_changed() {
+        time.set_relative_speed(speed.multiplier());
+    }
+}
+
+/// Leaving the game stops its clock: the next game starts on the first day at 1× speed.
+pub fn reset_simulation(
+    mut clock: ResMut<SimulationClock>,
+    mut speed: ResMut<SimulationSpeed>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    *clock = SimulationClock::default();
+    *speed = SimulationSpeed::default();
+    time.set_relative_speed(speed.multiplier());
+}
+
+/// Date and speed buttons, at the top of the screen.
+pub fn clock_panel(
+    mut contexts: EguiContexts,
+    clock: Res<SimulationClock>,
+    mut speed: ResMut<SimulationSpeed>,
+) {
+    let mut choice = *speed;
+    egui::Window::new("Clock")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 10.0))
+        .title_bar(false)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label(clock.date());
+                ui.separator();
+                for option in SimulationSpeed::ALL {
+                    ui.selectable_value(&mut choice, option, option.label());
+                }
+            });
+        });
+    if choice != *speed {
+        *speed = choice;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date() {
+        let mut clock = SimulationClock::default();
+        assert_eq!(clock.date(), "1 January, year 1");
+        assert!(clock.is_new_month());
+
+        clock.tick = TICKS_PER_DAY * (DAYS_PER_MONTH * 13 + 4) + 1;
+        assert_eq!(clock.date(), "5 February, year 2");
+        assert!(!clock.is_new_month());
+    }
+}
@@ -1,23 +1,821 @@
-use bevy::{gltf::Gltf, prelude::*};
+use crate::app_config::GameState;
+use crate::ingame_ui::AvailableTiles;
 use crate::tilemap::TileType;
+use crate::wfc::{EAST, NORTH, SOUTH, WEST, opposite};
+use bevy::asset::RecursiveDependencyLoadState;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
 
-/// Resource holding the Scene handles for each tile.
-/// Index 0 is `Handle::default()` for `TileType::Empty`.
+/// Manifest and rules shipped with the game, relative to the assets folder.
+const MANIFEST_PATH: &str = "tiles/manifest.ron";
+const RULES_PATH: &str = "tiles/rules.ron";
+
+/// Folder scanned for tile packs, next to the assets folder.
+pub const PACKS_DIR: &str = "tile_packs";
+/// Asset source serving the pack folders, so pack models load like any other asset.
+pub const PACKS_SOURCE: &str = "packs";
+
+/// Built-in copies, used on the web where the assets folder cannot be read synchronously.
+const DEFAULT_MANIFEST: &str = include_str!("../assets/tiles/manifest.ron");
+const DEFAULT_RULES: &str = include_str!("../assets/tiles/rules.ron");
+
+/// Broad family of a tile, used by the simulation and the planning view.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum TileCategory {
+    #[default]
+    None,
+    Residential,
+    Commercial,
+    Industrial,
+    Road,
+    Park,
+    Civic,
+}
+
+impl TileCategory {
+    /// Colour of the category in the planning view, and of placeholder boxes.
+    pub fn color(self) -> Color {
+        self.placeholder_style().0
+    }
+
+    /// Colour and height of the box drawn for tiles of this category without a model.
+    pub fn placeholder_style(self) -> (Color, f32) {
+        match self {
+            TileCategory::None => (Color::srgb(1.0, 0.0, 1.0), 0.5),
+            TileCategory::Residential => (Color::srgb(0.95, 0.75, 0.45), 0.6),
+            TileCategory::Commercial => (Color::srgb(0.3, 0.55, 0.95), 1.2),
+            TileCategory::Industrial => (Color::srgb(0.55, 0.45, 0.35), 0.8),
+            TileCategory::Road => (Color::srgb(0.25, 0.25, 0.25), 0.05),
+            TileCategory::Park => (Color::srgb(0.3, 0.75, 0.3), 0.1),
+            TileCategory::Civic => (Color::srgb(0.9, 0.9, 0.85), 0.9),
+        }
+    }
+}
+
+/// One entry of the tile manifest.
+#[derive(Deserialize, Clone, Debug)]
+pub struct TileDefinition {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub icon: String,
+    /// GLB file, relative to the assets folder.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Other looks of the same tile, one model is picked per cell.
+    #[serde(default)]
+    pub variants: Vec<String>,
+    /// Whether models get a small random rotation and scale, off for tiles that must line up.
+    #[serde(default = "default_jitter")]
+    pub jitter: bool,
+    #[serde(default = "default_scale")]
+    pub scale: (f32, f32, f32),
+    #[serde(default)]
+    pub weight: f32,
+    #[serde(default)]
+    pub category: TileCategory,
+    #[serde(default)]
+    pub cost: u32,
+    /// Paid every month while the tile stands.
+    #[serde(default)]
+    pub upkeep: u32,
+    #[serde(default = "default_footprint")]
+    pub footprint: (u32, u32),
+}
+
+fn default_scale() -> (f32, f32, f32) {
+    (1.0, 1.0, 1.0)
+}
+
+fn default_footprint() -> (u32, u32) {
+    (1, 1)
+}
+
+fn default_jitter() -> bool {
+    true
+}
+
+impl TileDefinition {
+    /// Every model of the tile, variants included.
+    pub fn models(&self) -> impl Iterator<Item = &String> {
+        self.model.iter().chain(&self.variants)
+    }
+
+    /// Number of cells covered along x and z.
+    pub fn footprint(&self) -> UVec2 {
+        let (x, z) = self.footprint;
+        UVec2::new(x.max(1), z.max(1))
+    }
+
+    /// Cells covered by this tile when anchored at (x, z).
+    pub fn footprint_cells(
+        &self,
+        x: usize,
+        z: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + use<> {
+        let size = self.footprint();
+        (0..size.y as usize)
+            .flat_map(move |dz| (0..size.x as usize).map(move |dx| (x + dx, z + dz)))
+    }
+
+    /// Offset from the anchor cell to the centre of the footprint.
+    pub fn footprint_centre(&self) -> Vec3 {
+        let size = self.footprint().as_vec2() - Vec2::ONE;
+        Vec3::new(size.x / 2.0, 0.0, size.y / 2.0)
+    }
+
+    /// Whether the model can be turned without changing the footprint: square footprints only.
+    pub fn rotates(&self) -> bool {
+        let size = self.footprint();
+        size.x == size.y
+    }
+
+    // To scale cases and models
+    pub fn scale(&self) -> Vec3 {
+        let (x, y, z) = self.scale;
+        Vec3::new(x, y, z)
+    }
+
+    /// Definition behind `TileType::EMPTY`.
+    fn empty() -> Self {
+        Self {
+            id: "empty".to_string(),
+            name: "Empty".to_string(),
+            icon: "❓".to_string(),
+            model: None,
+            variants: Vec::new(),
+            jitter: false,
+            scale: default_scale(),
+            weight: 0.0,
+            category: TileCategory::None,
+            cost: 0,
+            upkeep: 0,
+            footprint: default_footprint(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TileManifest {
+    tiles: Vec<TileDefinition>,
+}
+
+/// Side of a tile, in rules.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Side {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Side {
+    /// Matching WFC direction.
+    fn dir(self) -> usize {
+        match self {
+            Side::North => NORTH,
+            Side::South => SOUTH,
+            Side::East => EAST,
+            Side::West => WEST,
+        }
+    }
+}
+
+/// Two tiles that cannot be adjacent.
+#[derive(Deserialize, Clone, Debug)]
+pub struct AdjacencyRule {
+    pub a: String,
+    pub b: String,
+    /// Sides of `a` that `b` may not touch. Every side when empty.
+    #[serde(default)]
+    pub sides: Vec<Side>,
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// Every known tile and the adjacency rules between them, built by `load_tiles`.
+/// Index 0 is always `TileType::EMPTY`. Clones share the same data.
+#[derive(Resource, Clone)]
+pub struct TileCatalogue(Arc<CatalogueData>);
+
+#[derive(Clone)]
+struct CatalogueData {
+    tiles: Vec<TileDefinition>,
+    /// `compatible[a][b][dir]` is false when `b` cannot lie in direction `dir` from `a`.
+    compatible: Vec<Vec<[bool; 4]>>,
+    /// Keyed like `compatible`, by `(a, b, dir)`.
+    reasons: HashMap<(usize, usize, usize), String>,
+}
+
+impl TileCatalogue {
+    /// Manifest and rules shipped with the game, also used by tests.
+    pub fn builtin() -> Self {
+        Self::from_ron(DEFAULT_MANIFEST, DEFAULT_RULES).expect("built-in tile manifest is invalid")
+    }
+
+    pub fn from_ron(manifest: &str, rules: &str) -> Result<Self, String> {
+        let manifest: TileManifest =
+            ron::from_str(manifest).map_err(|e| format!("invalid tile manifest: {}", e))?;
+        let rules: Vec<AdjacencyRule> =
+            ron::from_str(rules).map_err(|e| format!("invalid rules file: {}", e))?;
+
+        let mut catalogue = Self(Arc::new(CatalogueData {
+            tiles: Vec::new(),
+            compatible: Vec::new(),
+            reasons: HashMap::new(),
+        }));
+        catalogue.push_tile(TileDefinition::empty())?;
+        for tile in manifest.tiles {
+            catalogue.push_tile(tile)?;
+        }
+        for rule in rules {
+            let unknown = |id: &str| format!("rule refers to unknown tile '{}'", id);
+            let a = catalogue.find(&rule.a).ok_or_else(|| unknown(&rule.a))?;
+            let b = catalogue.find(&rule.b).ok_or_else(|| unknown(&rule.b))?;
+            catalogue.forbid(a, b, &rule);
+        }
+        Ok(catalogue)
+    }
+
+    /// Merges a tile pack. Its ids are prefixed with `namespace:` and its models are
+    /// loaded from `model_root`. Rules may refer to pack tiles by their short id.
+    /// Returns the problems met, the pack is merged as far as possible.
+    /// Fails only when the pack manifest cannot be read at all.
+    pub fn add_pack(
+        &mut self,
+        namespace: &str,
+        manifest: &str,
+        rules: Option<&str>,
+        model_root: &str,
+    ) -> Result<Vec<String>, String> {
+        let manifest: TileManifest =
+            ron::from_str(manifest).map_err(|e| format!("invalid tile manifest: {}", e))?;
+        let mut problems = Vec::new();
+
+        let first = self.len();
+        for mut tile in manifest.tiles {
+            tile.id = format!("{}:{}", namespace, tile.id);
+            tile.model = tile.model.map(|model| format!("{}/{}", model_root, model));
+            for variant in tile.variants.iter_mut() {
+                *variant = format!("{}/{}", model_root, variant);
+            }
+            if let Err(err) = self.push_tile(tile) {
+                problems.push(err);
+            }
+        }
+
+        let rules: Vec<AdjacencyRule> = match rules.map(ron::from_str).transpose() {
+            Ok(rules) => rules.unwrap_or_default(),
+            Err(e) => {
+                problems.push(format!("invalid rules file: {}", e));
+                Vec::new()
+            }
+        };
+        for rule in rules {
+            let resolve = |id: &str| {
+                self.find(&format!("{}:{}", namespace, id))
+                    .or_else(|| self.find(id))
+                    .ok_or_else(|| format!("rule refers to unknown tile '{}'", id))
+            };
+            let (a, b) = match (resolve(&rule.a), resolve(&rule.b)) {
+                (Ok(a), Ok(b)) => (a, b),
+                (Err(err), _) | (_, Err(err)) => {
+                    problems.push(err);
+                    continue;
+                }
+            };
+            // Packs cannot change how the tiles of the game or of other packs behave together
+            if a.index() < first && b.index() < first {
+                problems.push(format!(
+                    "rule between '{}' and '{}' ignored, packs may only add rules for their own tiles",
+                    rule.a, rule.b
+                ));
+                continue;
+            }
+            self.forbid(a, b, &rule);
+        }
+        Ok(problems)
+    }
+
+    fn push_tile(&mut self, tile: TileDefinition) -> Result<(), String> {
+        if self.find(&tile.id).is_some() {
+            return Err(format!("tile id '{}' is defined twice", tile.id));
+        }
+        // Only shared once loading is done, nothing is copied here
+        let data = Arc::make_mut(&mut self.0);
+        data.tiles.push(tile);
+        for row in data.compatible.iter_mut() {
+            row.push([true; 4]);
+        }
+        data.compatible.push(vec![[true; 4]; data.tiles.len()]);
+        Ok(())
+    }
+
+    fn forbid(&mut self, a: TileType, b: TileType, rule: &AdjacencyRule) {
+        let (a, b) = (a.index(), b.index());
+        let data = Arc::make_mut(&mut self.0);
+        let dirs: Vec<usize> = if rule.sides.is_empty() {
+            vec![NORTH, SOUTH, EAST, WEST]
+        } else {
+            rule.sides.iter().map(|side| side.dir()).collect()
+        };
+        for dir in dirs {
+            data.compatible[a][b][dir] = false;
+            data.compatible[b][a][opposite(dir)] = false;
+            data.reasons.insert((a, b, dir), rule.reason.clone());
+            data.reasons
+                .insert((b, a, opposite(dir)), rule.reason.clone());
+        }
+    }
+
+    /// Number of tiles, `TileType::EMPTY` included.
+    pub fn len(&self) -> usize {
+        self.0.tiles.len()
+    }
+
+    pub fn tiles(&self) -> &[TileDefinition] {
+        &self.0.tiles
+    }
+
+    pub fn get(&self, tile_type: TileType) -> &TileDefinition {
+        &self.0.tiles[tile_type.index()]
+    }
+
+    pub fn name(&self, tile_type: TileType) -> &str {
+        &self.get(tile_type).name
+    }
+
+    pub fn find(&self, id: &str) -> Option<TileType> {
+        self.0
+            .tiles
+            .iter()
+            .position(|t| t.id == id)
+            .map(TileType::new)
+    }
+
+    /// Every tile that can be placed, `TileType::EMPTY` excluded.
+    pub fn placeable(&self) -> impl Iterator<Item = TileType> {
+        (1..self.len()).map(TileType::new)
+    }
+
+    /// Whether `b` may lie in direction `dir` from `a`.
+    pub fn compatible(&self, dir: usize, a: usize, b: usize) -> bool {
+        self.0.compatible[a][b][dir]
+    }
+
+    /// Whether `a` and `b` may touch on every side.
+    pub fn always_compatible(&self, a: usize, b: usize) -> bool {
+        self.0.compatible[a][b].iter().all(|&ok| ok)
+    }
+
+    /// Why `b` cannot lie in direction `dir` from `a`.
+    pub fn reason(&self, dir: usize, a: usize, b: usize) -> &str {
+        self.0
+            .reasons
+            .get(&(a, b, dir))
+            .map(|r| r.as_str())
+            .filter(|r| !r.is_empty())
+            .unwrap_or("Incompatible neighbours")
+    }
+}
+
+/// Severity of a `TileLog` entry.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LogLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+/// What happened while loading the tile catalogue and the tile packs, shown in game.
+#[derive(Resource, Default)]
+pub struct TileLog {
+    pub entries: Vec<(LogLevel, String)>,
+    /// Whether the log window is shown. Opened when something went wrong.
+    pub open: bool,
+}
+
+impl TileLog {
+    pub fn info(&mut self, message: String) {
+        info!("{}", message);
+        self.entries.push((LogLevel::Info, message));
+    }
+
+    pub fn warn(&mut self, message: String) {
+        warn!("{}", message);
+        self.entries.push((LogLevel::Warning, message));
+        self.open = true;
+    }
+
+    pub fn error(&mut self, message: String) {
+        error!("{}", message);
+        self.entries.push((LogLevel::Error, message));
+        self.open = true;
+    }
+}
+
+/// Reads the manifest and rules from the assets folder, then merges every tile pack found
+/// in `tile_packs/`. The web build only has the built-in copies.
+fn read_catalogue(log: &mut TileLog) -> TileCatalogue {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let base = bevy::asset::io::file::FileAssetReader::get_base_path();
+        let assets = base.join("assets");
+        let read = |path: &str| {
+            std::fs::read_to_string(assets.join(path))
+                .map_err(|e| format!("cannot read {}: {}", path, e))
+        };
+        let mut catalogue = read(MANIFEST_PATH)
+            .and_then(|manifest| TileCatalogue::from_ron(&manifest, &read(RULES_PATH)?))
+            .unwrap_or_else(|err| {
+                log.error(format!(
+                    "Falling back to the built-in tile catalogue: {}",
+                    err
+                ));
+                TileCatalogue::builtin()
+            });
+        read_packs(&mut catalogue, &base.join(PACKS_DIR), log);
+        catalogue
+    }
+
+    // The browser cannot list a folder, the packs would be skipped without a word
+    #[cfg(target_arch = "wasm32")]
+    {
+        log.warn(format!(
+            "Tile packs are not supported in the web build, '{}' is ignored",
+            PACKS_DIR
+        ));
+        TileCatalogue::builtin()
+    }
+}
+
+/// Merges every `<dir>/<pack>/manifest.ron`, in alphabetical order.
+/// The folder name is used as the namespace of the pack.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_packs(catalogue: &mut TileCatalogue, dir: &std::path::Path, log: &mut TileLog) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut packs: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    packs.sort();
+
+    for pack in packs {
+        let folder = dir.join(&pack);
+        let Ok(manifest) = std::fs::read_to_string(folder.join("manifest.ron")) else {
+            log.warn(format!("Tile pack '{}' has no manifest.ron, skipped", pack));
+            continue;
+        };
+        let rules = std::fs::read_to_string(folder.join("rules.ron")).ok();
+        let model_root = format!("{}://{}", PACKS_SOURCE, pack);
+
+        let before = catalogue.len();
+        match catalogue.add_pack(&pack, &manifest, rules.as_deref(), &model_root) {
+            Ok(problems) => {
+                for problem in problems {
+                    log.warn(format!("Tile pack '{}': {}", pack, problem));
+                }
+                log.info(format!(
+                    "Tile pack '{}': {} tiles added",
+                    pack,
+                    catalogue.len() - before
+                ));
+            }
+            Err(err) => log.error(format!("Tile pack '{}' skipped: {}", pack, err)),
+        }
+    }
+}
+
+/// One mesh of a tile model, with its transform relative to the model root.
+#[derive(Clone)]
+pub struct ModelPart {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+    pub transform: Transform,
+}
+
+/// Resource holding the models of each tile, one per variant.
+/// Empty for `TileType::EMPTY`.
 #[derive(Resource)]
 pub struct TileAssets {
-    pub tiles: Vec<Handle<Scene>>,
+    pub scenes: Vec<Vec<Handle<Scene>>>,
+    /// Meshes of each variant, flattened out of `scenes` once loaded. Tiles are spawned
+    /// from these rather than as scenes: every tile of a kind reuses the same mesh and material
+    /// handles, which lets Bevy's automatic batching merge their draw calls.
+    pub models: Vec<Vec<Vec<ModelPart>>>,
 }
 
-pub fn load_tiles(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // Allocate once with the correct length (= highest enum value + 1)
-    let mut tiles = vec![Handle::<Scene>::default(); TileType::Park.index() + 1];
+impl TileAssets {
+    /// Number of models done loading (or failed), and total number of models.
+    /// Placeholders are not loaded from files and are left out.
+    pub fn progress(
+        &self,
+        asset_server: &AssetServer,
+        catalogue: &TileCatalogue,
+    ) -> (usize, usize) {
+        self.scenes
+            .iter()
+            .zip(catalogue.tiles())
+            .filter(|(_, tile)| tile.models().next().is_some())
+            .flat_map(|(handles, _)| handles)
+            .fold((0, 0), |(done, total), handle| {
+                match asset_server.recursive_dependency_load_state(handle) {
+                    RecursiveDependencyLoadState::Loaded
+                    | RecursiveDependencyLoadState::Failed(_) => (done + 1, total + 1),
+                    _ => (done, total + 1),
+                }
+            })
+    }
+
+    /// Index of the model variant shown on a cell, picked from `hash`.
+    pub fn variant(&self, tile_type: TileType, hash: u64) -> usize {
+        let count = self.scenes[tile_type.index()].len().max(1);
+        (hash % count as u64) as usize
+    }
 
-    for tile_type in TileType::ALL {
-        if let Some(path) = tile_type.scene_path() {
-            let handle: Handle<Scene> = asset_server.load(path);
-            tiles[tile_type.index()] = handle;
+    /// Meshes of a model variant, empty until loading is done.
+    pub fn parts(&self, tile_type: TileType, variant: usize) -> &[ModelPart] {
+        self.models
+            .get(tile_type.index())
+            .and_then(|variants| variants.get(variant))
+            .map_or(&[], |parts| parts.as_slice())
+    }
+}
+
+pub fn load_tiles(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut scenes: ResMut<Assets<Scene>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mut log = TileLog::default();
+    let catalogue = read_catalogue(&mut log);
+    log.info(format!("Loaded {} tile types", catalogue.len() - 1));
+
+    let scenes = catalogue
+        .tiles()
+        .iter()
+        .enumerate()
+        .map(|(index, tile)| {
+            let mut handles: Vec<_> = tile
+                .models()
+                .map(|model| asset_server.load(format!("{}#Scene0", model)))
+                .collect();
+            if handles.is_empty() && index != TileType::EMPTY.index() {
+                handles.push(scenes.add(placeholder_scene(tile, &mut meshes, &mut materials)));
+            }
+            handles
+        })
+        .collect();
+
+    commands.insert_resource(TileAssets {
+        scenes,
+        models: Vec::new(),
+    });
+    commands.insert_resource(AvailableTiles {
+        tiles: catalogue.placeable().collect(),
+    });
+    commands.insert_resource(catalogue);
+    commands.insert_resource(log);
+}
+
+/// Leaves `GameState::Loading` once every tile model is loaded or failed.
+/// Failed variants are dropped, tiles left without any model get a placeholder so they stay visible.
+#[allow(clippy::too_many_arguments)]
+pub fn check_tile_assets(
+    asset_server: Res<AssetServer>,
+    catalogue: Res<TileCatalogue>,
+    mut tile_assets: ResMut<TileAssets>,
+    mut log: ResMut<TileLog>,
+    mut scenes: ResMut<Assets<Scene>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let (done, total) = tile_assets.progress(&asset_server, &catalogue);
+    if done < total {
+        return;
+    }
+
+    let tile_assets = tile_assets.as_mut();
+    for (handles, tile) in tile_assets.scenes.iter_mut().zip(catalogue.tiles()) {
+        handles.retain(
+            |handle| match asset_server.recursive_dependency_load_state(handle) {
+                RecursiveDependencyLoadState::Failed(err) => {
+                    let path = handle.path().map(|p| p.to_string()).unwrap_or_default();
+                    log.warn(format!(
+                        "Tile '{}': {} failed to load ({})",
+                        tile.id, path, err
+                    ));
+                    false
+                }
+                _ => true,
+            },
+        );
+        if handles.is_empty() && tile.models().next().is_some() {
+            log.warn(format!(
+                "Tile '{}': no model left, using a placeholder",
+                tile.id
+            ));
+            let scene = placeholder_scene(tile, &mut meshes, &mut materials);
+            handles.push(scenes.add(scene));
         }
     }
 
-    commands.insert_resource(TileAssets { tiles });
+    tile_assets.models = tile_assets
+        .scenes
+        .iter()
+        .zip(catalogue.tiles())
+        .map(|(handles, tile)| {
+            handles
+                .iter()
+                .filter_map(|handle| {
+                    let (parts, skipped) = flatten_scene(scenes.get(handle)?);
+                    if skipped > 0 {
+                        let path = handle.path().map(|p| p.to_string()).unwrap_or_default();
+                        log.warn(format!(
+                            "Tile '{}': {} has {} meshes without a standard material, they are not drawn",
+                            tile.id, path, skipped
+                        ));
+                    }
+                    Some(parts)
+                })
+                .collect()
+        })
+        .collect();
+
+    next_state.set(GameState::MainMenu);
+}
+
+/// Every mesh of a scene, with its transform relative to the scene root, and the number of
+/// meshes left out because their material is not a `StandardMaterial`.
+fn flatten_scene(scene: &Scene) -> (Vec<ModelPart>, usize) {
+    let world = &scene.world;
+    let mut skipped = 0;
+    let parts = world
+        .iter_entities()
+        .filter_map(|entity| {
+            let mesh = entity.get::<Mesh3d>()?;
+            let Some(material) = entity.get::<MeshMaterial3d<StandardMaterial>>() else {
+                skipped += 1;
+                return None;
+            };
+
+            let mut transform = entity.get::<Transform>().copied().unwrap_or_default();
+            let mut parent = entity.get::<ChildOf>().map(|child_of| child_of.parent());
+            while let Some(current) = parent {
+                let current = world.entity(current);
+                if let Some(parent_transform) = current.get::<Transform>() {
+                    transform = parent_transform.mul_transform(transform);
+                }
+                parent = current.get::<ChildOf>().map(|child_of| child_of.parent());
+            }
+
+            Some(ModelPart {
+                mesh: mesh.0.clone(),
+                material: material.0.clone(),
+                transform,
+            })
+        })
+        .collect();
+    (parts, skipped)
+}
+
+/// Scene holding a box over the tile footprint, coloured and sized after the tile category.
+/// Shown for tiles without a model or whose model failed to load.
+fn placeholder_scene(
+    tile: &TileDefinition,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+) -> Scene {
+    let (color, height) = tile.category.placeholder_style();
+    let footprint = tile.footprint().as_vec2() - Vec2::splat(0.2);
+    let size = Vec3::new(footprint.x, height, footprint.y);
+
+    // Tiles are spawned with the model scale, undo it so the box keeps its size
+    let scale = tile.scale();
+    let mut world = World::new();
+    world.spawn((
+        Mesh3d(meshes.add(Cuboid::from_size(size))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: color,
+            perceptual_roughness: 0.9,
+            ..default()
+        })),
+        Transform {
+            translation: Vec3::Y * height / 2.0 / scale,
+            scale: Vec3::ONE / scale,
+            ..default()
+        },
+    ));
+    Scene::new(world)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_catalogue() {
+        let catalogue = TileCatalogue::from_ron(DEFAULT_MANIFEST, DEFAULT_RULES).unwrap();
+        let res = catalogue.find("residential").unwrap().index();
+        let ind = catalogue.find("industrial").unwrap().index();
+        let road = catalogue.find("road").unwrap().index();
+
+        assert_eq!(catalogue.get(TileType::EMPTY).id, "empty");
+        for dir in [NORTH, SOUTH, EAST, WEST] {
+            assert!(!catalogue.compatible(dir, res, ind));
+            assert!(!catalogue.compatible(dir, ind, res));
+            assert!(catalogue.compatible(dir, res, road));
+        }
+        assert_eq!(
+            catalogue
+                .get(catalogue.find("stadium").unwrap())
+                .footprint(),
+            UVec2::new(2, 2)
+        );
+    }
+
+    #[test]
+    fn test_directional_rule() {
+        let manifest = r#"(tiles: [(id: "a", name: "A"), (id: "b", name: "B")])"#;
+        let rules = r#"[(a: "a", b: "b", sides: [North])]"#;
+        let catalogue = TileCatalogue::from_ron(manifest, rules).unwrap();
+        let (a, b) = (1, 2);
+
+        assert!(!catalogue.compatible(NORTH, a, b));
+        assert!(!catalogue.compatible(SOUTH, b, a));
+        assert!(catalogue.compatible(SOUTH, a, b));
+        assert!(catalogue.compatible(EAST, a, b));
+        assert!(catalogue.compatible(NORTH, b, a));
+        assert!(!catalogue.always_compatible(a, b));
+    }
+
+    #[test]
+    fn test_reason_per_side() {
+        let manifest = r#"(tiles: [(id: "a", name: "A"), (id: "b", name: "B")])"#;
+        let rules = r#"[
+            (a: "a", b: "b", sides: [North], reason: "Shade"),
+            (a: "a", b: "b", sides: [East], reason: "Noise"),
+        ]"#;
+        let catalogue = TileCatalogue::from_ron(manifest, rules).unwrap();
+
+        assert_eq!(catalogue.reason(NORTH, 1, 2), "Shade");
+        assert_eq!(catalogue.reason(SOUTH, 2, 1), "Shade");
+        assert_eq!(catalogue.reason(EAST, 1, 2), "Noise");
+        assert_eq!(catalogue.reason(WEST, 2, 1), "Noise");
+    }
+
+    #[test]
+    fn test_invalid_catalogues() {
+        let manifest = r#"(tiles: [(id: "a", name: "A"), (id: "a", name: "A again")])"#;
+        assert!(TileCatalogue::from_ron(manifest, "[]").is_err());
+
+        let manifest = r#"(tiles: [(id: "a", name: "A")])"#;
+        let rules = r#"[(a: "a", b: "missing")]"#;
+        assert!(TileCatalogue::from_ron(manifest, rules).is_err());
+    }
+
+    #[test]
+    fn test_add_pack() {
+        let mut catalogue = TileCatalogue::from_ron(DEFAULT_MANIFEST, DEFAULT_RULES).unwrap();
+        let base = catalogue.len();
+        let manifest = r#"#![enable(implicit_some)]
+            (tiles: [
+                (id: "tower", name: "Tower", model: "tower/tile.glb"),
+                (id: "tower", name: "Tower again"),
+                (id: "farm", name: "Farm"),
+            ])"#;
+        let rules = r#"[
+            (a: "tower", b: "residential"),
+            (a: "farm", b: "missing"),
+            (a: "park", b: "road"),
+        ]"#;
+
+        let problems = catalogue
+            .add_pack("city", manifest, Some(rules), "packs://city")
+            .unwrap();
+        // Duplicate id, unknown tile and a rule between base tiles
+        assert_eq!(problems.len(), 3);
+        assert_eq!(catalogue.len(), base + 2);
+
+        let tower = catalogue.find("city:tower").unwrap();
+        let res = catalogue.find("residential").unwrap();
+        let park = catalogue.find("park").unwrap();
+        let road = catalogue.find("road").unwrap();
+        assert_eq!(
+            catalogue.get(tower).model.as_deref(),
+            Some("packs://city/tower/tile.glb")
+        );
+        assert!(!catalogue.always_compatible(tower.index(), res.index()));
+        assert!(catalogue.always_compatible(park.index(), road.index()));
+
+        assert!(catalogue.add_pack("broken", "(", None, "").is_err());
+    }
 }
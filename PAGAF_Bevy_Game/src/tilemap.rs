@@ -1,15 +1,19 @@
-use crate::tile_loader::TileAssets;
+use crate::app_config::GameState;
+use crate::budget::Budget;
+use crate::picking::{HoveredCell, PlacedTile};
+use crate::tile_loader::{TileAssets, TileCatalogue};
 use crate::undo_redo::{Action, UndoRedo};
-use crate::wfc::WFCState;
+use crate::wfc::{PlacementError, WFCGrid, WFCState};
+use bevy::ecs::spawn::SpawnIter;
 use bevy::prelude::*;
-use bevy_egui::EguiContexts;
+use bevy_egui::{EguiContexts, egui};
+use std::f32::consts::FRAC_PI_2;
 
+// Overlay quad above a grid cell, showing whether the selected tile fits there
 #[derive(Component)]
-pub struct PlacementHighlight;
-
-#[derive(Component)]
-pub struct ValidationIndicator {
-    pub valid: bool,
+pub struct PlacementHighlight {
+    x: usize,
+    z: usize,
 }
 
 // Tile component storing type and position
@@ -17,68 +21,36 @@ pub struct ValidationIndicator {
 pub struct Tile {
     pub tile_type: TileType,
     pub position: IVec2,
+    /// Cell holding the building covering this cell (its top-left footprint cell).
+    pub anchor: IVec2,
+    /// Quarter turns of the building model, clockwise. Only shown for `TileType::rotates` tiles.
+    pub rotation: u8,
 }
 
-// Enum representing different types of tiles
-#[repr(usize)]
-#[derive(Clone, Copy, PartialEq, Debug)]
-pub enum TileType {
-    Empty = 0,
-    Residential = 1,
-    Commercial = 2,
-    Industrial = 3,
-    Road = 4,
-    Park = 5,
-}
+/// Type of a tile: index into the tile catalogue loaded from `assets/tiles/manifest.ron`.
+/// Definitions are looked up in the `TileCatalogue` resource.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct TileType(usize);
 
 impl TileType {
-    pub const ALL: [TileType; 5] = [
-        TileType::Residential,
-        TileType::Commercial,
-        TileType::Industrial,
-        TileType::Road,
-        TileType::Park,
-    ];
-
-    /// Index used in vectors and for file names.
-    pub fn index(self) -> usize {
-        self as usize
-    }
+    pub const EMPTY: TileType = TileType(0);
 
-    /// Path of the GLB scene for this tile.
-    pub fn scene_path(self) -> Option<String> {
-        match self {
-            TileType::Empty => None,
-            _ => Some(format!(
-                "models/tiles/tile_{}/tile.glb#Scene0",
-                self.index()
-            )),
-        }
+    /// Tile at `index` in the catalogue, without checking it exists.
+    pub const fn new(index: usize) -> Self {
+        Self(index)
     }
 
-    pub fn from_index(index: usize) -> Option<Self> {
-        match index {
-            0 => Some(TileType::Empty),
-            1 => Some(TileType::Residential),
-            2 => Some(TileType::Commercial),
-            3 => Some(TileType::Industrial),
-            4 => Some(TileType::Road),
-            5 => Some(TileType::Park),
-            _ => None,
-        }
+    /// Index used in vectors.
+    pub fn index(self) -> usize {
+        self.0
     }
+}
 
-    // To scale cases and models
-    pub fn scale(self) -> Vec3 {
-        match self {
-            TileType::Residential => Vec3::splat(0.1),
-            TileType::Commercial => Vec3::splat(0.05),
-            TileType::Industrial => Vec3::splat(0.2),
-            TileType::Road => Vec3::splat(0.25),
-            TileType::Park => Vec3::splat(0.14),
-            TileType::Empty => Vec3::ONE,
-        }
-    }
+/// Sent for every cell of the `TileMap` whose tile changes, so views can update incrementally.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CellChanged {
+    pub x: usize,
+    pub z: usize,
 }
 
 // Resource to store the map data
@@ -88,12 +60,265 @@ pub struct TileMap {
     pub width: usize,
     pub height: usize,
     pub entities: Vec<Vec<Option<Entity>>>, // Track spawned tile entities
+    /// Picks the model variant and jitter of each cell.
+    pub seed: u64,
+    /// Tiles the map is made of.
+    catalogue: TileCatalogue,
 }
 
-impl Default for TileMap {
-    fn default() -> Self {
-        let width = 50;
-        let height = 50;
+impl TileMap {
+    /// Places a tile anchored at (x, z) over its whole footprint and spawns its model,
+    /// turned by `rotation` quarter turns. Any building overlapping the footprint is removed
+    /// first. `TileType::EMPTY` clears the cell.
+    pub fn set_tile(
+        &mut self,
+        commands: &mut Commands,
+        tile_assets: &TileAssets,
+        x: usize,
+        z: usize,
+        tile_type: TileType,
+        rotation: u8,
+    ) {
+        if tile_type == TileType::EMPTY {
+            self.remove_building(commands, x, z);
+            return;
+        }
+
+        let rotation = rotation % 4;
+        let footprint: Vec<_> = self
+            .catalogue
+            .get(tile_type)
+            .footprint_cells(x, z)
+            .collect();
+        for &(cx, cz) in &footprint {
+            self.remove_building(commands, cx, cz);
+        }
+        for &(cx, cz) in &footprint {
+            let tile = &mut self.tiles[cz][cx];
+            tile.tile_type = tile_type;
+            tile.anchor = IVec2::new(x as i32, z as i32);
+            tile.rotation = rotation;
+            commands.send_event(CellChanged { x: cx, z: cz });
+        }
+        self.entities[z][x] = Some(spawn_tile(
+            commands,
+            &self.catalogue,
+            tile_assets,
+            tile_type,
+            self.seed,
+            (x, z),
+            rotation,
+        ));
+    }
+
+    /// Turns the building anchored at (x, z) to `rotation` quarter turns and respawns its model.
+    pub fn set_rotation(
+        &mut self,
+        commands: &mut Commands,
+        tile_assets: &TileAssets,
+        x: usize,
+        z: usize,
+        rotation: u8,
+    ) {
+        let tile_type = self.tiles[z][x].tile_type;
+        if tile_type == TileType::EMPTY {
+            return;
+        }
+        let rotation = rotation % 4;
+        for (cx, cz) in self.catalogue.get(tile_type).footprint_cells(x, z) {
+            self.tiles[cz][cx].rotation = rotation;
+        }
+        if let Some(entity) = self.entities[z][x].take() {
+            commands.entity(entity).despawn();
+        }
+        self.entities[z][x] = Some(spawn_tile(
+            commands,
+            &self.catalogue,
+            tile_assets,
+            tile_type,
+            self.seed,
+            (x, z),
+            rotation,
+        ));
+    }
+
+    /// Removes the building covering (x, z), whatever cell of its footprint is given.
+    /// Returns its anchor and type.
+    pub fn remove_building(
+        &mut self,
+        commands: &mut Commands,
+        x: usize,
+        z: usize,
+    ) -> Option<(usize, usize, TileType)> {
+        let tile = &self.tiles[z][x];
+        let tile_type = tile.tile_type;
+        if tile_type == TileType::EMPTY {
+            return None;
+        }
+
+        let (ax, az) = (tile.anchor.x as usize, tile.anchor.y as usize);
+        if let Some(entity) = self.entities[az][ax].take() {
+            commands.entity(entity).despawn();
+        }
+        for (cx, cz) in self.catalogue.get(tile_type).footprint_cells(ax, az) {
+            let tile = &mut self.tiles[cz][cx];
+            tile.tile_type = TileType::EMPTY;
+            tile.anchor = tile.position;
+            tile.rotation = 0;
+            commands.send_event(CellChanged { x: cx, z: cz });
+        }
+        Some((ax, az, tile_type))
+    }
+
+    /// Removes every placed tile.
+    pub fn clear(&mut self, commands: &mut Commands) {
+        for (tiles, entities) in self.tiles.iter_mut().zip(self.entities.iter_mut()) {
+            for (tile, entity) in tiles.iter_mut().zip(entities.iter_mut()) {
+                if tile.tile_type != TileType::EMPTY {
+                    commands.send_event(CellChanged {
+                        x: tile.position.x as usize,
+                        z: tile.position.y as usize,
+                    });
+                }
+                tile.tile_type = TileType::EMPTY;
+                tile.anchor = tile.position;
+                tile.rotation = 0;
+                if let Some(entity) = entity.take() {
+                    commands.entity(entity).despawn();
+                }
+            }
+        }
+    }
+}
+
+/// Largest random rotation of a model around its vertical axis, in radians.
+const JITTER_ROTATION: f32 = 0.1;
+/// Largest random change of the model scale.
+const JITTER_SCALE: f32 = 0.06;
+
+/// Pseudo-random value for a cell of a map (splitmix64), so a cell always looks the same.
+pub fn cell_hash(seed: u64, cell: IVec2) -> u64 {
+    let mut h = seed ^ ((cell.x as u32 as u64) << 32 | cell.y as u32 as u64);
+    h = h.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+/// Model of a tile anchored at `cell`, placed at `translation` and turned by `rotation` quarter turns.
+/// The variant and the jitter are picked from the map seed and the cell.
+pub fn tile_bundle(
+    catalogue: &TileCatalogue,
+    tile_assets: &TileAssets,
+    tile_type: TileType,
+    seed: u64,
+    cell: IVec2,
+    translation: Vec3,
+    rotation: u8,
+) -> impl Bundle {
+    let hash = cell_hash(seed, cell);
+    let transform = tile_transform(catalogue, tile_type, hash, translation, rotation);
+
+    // One plain entity per mesh. There is no explicit instancing, sharing the loaded handles
+    // is what lets the renderer batch the draws of identical tiles
+    let parts: Vec<_> = tile_assets
+        .parts(tile_type, tile_assets.variant(tile_type, hash))
+        .iter()
+        .map(|part| {
+            (
+                Mesh3d(part.mesh.clone()),
+                MeshMaterial3d(part.material.clone()),
+                part.transform,
+            )
+        })
+        .collect();
+    (
+        transform,
+        Visibility::default(),
+        Children::spawn(SpawnIter(parts.into_iter())),
+    )
+}
+
+/// Same model as `tile_bundle`, spawned as a full scene hierarchy.
+pub fn tile_scene_bundle(
+    catalogue: &TileCatalogue,
+    tile_assets: &TileAssets,
+    tile_type: TileType,
+    seed: u64,
+    cell: IVec2,
+    translation: Vec3,
+    rotation: u8,
+) -> (SceneRoot, Transform) {
+    let hash = cell_hash(seed, cell);
+    let scene = tile_assets.scenes[tile_type.index()]
+        .get(tile_assets.variant(tile_type, hash))
+        .cloned()
+        .unwrap_or_default();
+    (
+        SceneRoot(scene),
+        tile_transform(catalogue, tile_type, hash, translation, rotation),
+    )
+}
+
+/// Model scale and rotation of the tile, with its jitter.
+fn tile_transform(
+    catalogue: &TileCatalogue,
+    tile_type: TileType,
+    hash: u64,
+    translation: Vec3,
+    rotation: u8,
+) -> Transform {
+    let definition = catalogue.get(tile_type);
+    let mut transform = Transform {
+        translation,
+        scale: definition.scale(),
+        ..default()
+    };
+    if definition.rotates() {
+        transform.rotation = Quat::from_rotation_y(-FRAC_PI_2 * rotation as f32);
+    }
+    if definition.jitter {
+        // Two values in -1..1 from different bits than the variant index
+        let a = ((hash >> 16) & 0xffff) as f32 / 32767.5 - 1.0;
+        let b = ((hash >> 32) & 0xffff) as f32 / 32767.5 - 1.0;
+        transform.rotate_y(a * JITTER_ROTATION);
+        transform.scale *= 1.0 + b * JITTER_SCALE;
+    }
+    transform
+}
+
+/// Spawns the model of a tile at the given cell.
+pub fn spawn_tile(
+    commands: &mut Commands,
+    catalogue: &TileCatalogue,
+    tile_assets: &TileAssets,
+    tile_type: TileType,
+    seed: u64,
+    (x, z): (usize, usize),
+    rotation: u8,
+) -> Entity {
+    let cell = IVec2::new(x as i32, z as i32);
+    let translation =
+        Vec3::new(x as f32, 0.0, z as f32) + catalogue.get(tile_type).footprint_centre();
+    commands
+        .spawn((
+            tile_bundle(
+                catalogue,
+                tile_assets,
+                tile_type,
+                seed,
+                cell,
+                translation,
+                rotation,
+            ),
+            PlacedTile { x, z },
+        ))
+        .id()
+}
+
+impl TileMap {
+    /// Empty map of `width` × `height` cells.
+    pub fn new(width: usize, height: usize, catalogue: &TileCatalogue) -> Self {
         let mut tiles = Vec::with_capacity(height);
         let mut entities = Vec::with_capacity(height);
 
@@ -102,8 +327,10 @@ impl Default for TileMap {
             let mut entity_row = Vec::with_capacity(width);
             for x in 0..width {
                 row.push(Tile {
-                    tile_type: TileType::Empty,
+                    tile_type: TileType::EMPTY,
                     position: IVec2::new(x as i32, y as i32),
+                    anchor: IVec2::new(x as i32, y as i32),
+                    rotation: 0,
                 });
                 entity_row.push(None);
             }
@@ -116,25 +343,29 @@ impl Default for TileMap {
             width,
             height,
             entities,
+            seed: rand::random(),
+            catalogue: catalogue.clone(),
         }
     }
 }
 
 // Marker component for grid tiles
 #[derive(Component)]
-struct GridTile;
+pub struct GridTile;
 
 // Resource for highlighting materials
 #[derive(Resource)]
 pub struct HighlightMaterials {
-    valid: Handle<StandardMaterial>,
-    invalid: Handle<StandardMaterial>,
-    preview: Handle<StandardMaterial>,
+    pub valid: Handle<StandardMaterial>,
+    pub invalid: Handle<StandardMaterial>,
+    pub preview: Handle<StandardMaterial>,
+    pub cell_mesh: Handle<Mesh>,
 }
 
 // Setup function to create the grid mesh
 pub fn setup_grid(
     mut commands: Commands,
+    catalogue: Res<TileCatalogue>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
@@ -164,194 +395,373 @@ pub fn setup_grid(
             alpha_mode: AlphaMode::Blend,
             ..default()
         }),
+        cell_mesh: tile_mesh,
     };
 
-    commands.insert_resource(highlight_materials);
-
-    for x in 0..grid_size {
-        for z in 0..grid_size {
+    // Spawned once, only their visibility and material change afterwards
+    for z in 0..grid_size {
+        for x in 0..grid_size {
             commands.spawn((
-                Mesh3d(tile_mesh.clone()),
-                MeshMaterial3d(tile_material.clone()),
-                Transform::from_xyz(x as f32 * tile_size, 0.0, z as f32 * tile_size),
-                GridTile,
+                Mesh3d(highlight_materials.cell_mesh.clone()),
+                MeshMaterial3d(highlight_materials.valid.clone()),
+                Transform::from_xyz(x as f32, 0.02, z as f32),
+                Visibility::Hidden,
+                PlacementHighlight { x, z },
             ));
         }
     }
 
-    commands.insert_resource(TileMap::default());
+    commands.insert_resource(highlight_materials);
+
+    // A single plane for the whole ground, cells are centred on integer coordinates
+    let size = grid_size as f32 * tile_size;
+    let centre = (size - tile_size) / 2.0;
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(size, size))),
+        MeshMaterial3d(tile_material),
+        Transform::from_xyz(centre, 0.0, centre),
+        GridTile,
+        Pickable::default(),
+    ));
+
+    // Created once the tile catalogue is loaded, the cells depend on the tiles
+    commands.insert_resource(TileMap::new(grid_size, grid_size, &catalogue));
+    commands.insert_resource(WFCState {
+        grid: WFCGrid::new(grid_size, grid_size, &catalogue),
+    });
 }
 
+// Model of the selected tile with its footprint outlined, moved to the hovered cell
+#[derive(Component)]
+pub struct TilePreview;
+
+// Outline of one footprint cell of the preview
+#[derive(Component)]
+pub struct PreviewOutline;
+
+fn spawn_preview(
+    commands: &mut Commands,
+    catalogue: &TileCatalogue,
+    tile_assets: &TileAssets,
+    highlight_materials: &HighlightMaterials,
+    seed: u64,
+    tile_type: TileType,
+) -> Entity {
+    commands
+        .spawn((
+            TilePreview,
+            Transform::default(),
+            Visibility::Hidden,
+            StateScoped(GameState::InGame),
+        ))
+        .with_children(|parent| {
+            let definition = catalogue.get(tile_type);
+            parent.spawn(tile_bundle(
+                catalogue,
+                tile_assets,
+                tile_type,
+                seed,
+                IVec2::ZERO,
+                definition.footprint_centre(),
+                0,
+            ));
+            for (cx, cz) in definition.footprint_cells(0, 0) {
+                parent.spawn((
+                    PreviewOutline,
+                    Mesh3d(highlight_materials.cell_mesh.clone()),
+                    MeshMaterial3d(highlight_materials.preview.clone()),
+                    Transform::from_xyz(cx as f32, 0.01, cz as f32),
+                ));
+            }
+        })
+        .id()
+}
+
+/// Shows the selected tile on the hovered cell, and why it cannot go there.
+/// Placing it is done by `picking::on_click`. The preview is only respawned when another
+/// tile is selected, moving it just updates its transform and outline colour.
+#[allow(clippy::too_many_arguments)]
 pub fn place_tile_preview(
     mut commands: Commands,
+    catalogue: Res<TileCatalogue>,
     tile_assets: Res<TileAssets>,
-    mut selected_tile: ResMut<SelectedTile>,
-    mouse_input: Res<ButtonInput<MouseButton>>,
-    camera: Query<(&Camera, &GlobalTransform)>,
-    windows: Query<&Window>,
-    mut tile_map: ResMut<TileMap>,
-    mut wfc_state: ResMut<WFCState>,
-    mut undo_redo: ResMut<UndoRedo>,
-    mut preview: Local<Option<Entity>>,
+    selected_tile: Res<SelectedTile>,
+    hovered: Res<HoveredCell>,
+    tile_map: Res<TileMap>,
+    wfc_state: Res<WFCState>,
+    highlight_materials: Res<HighlightMaterials>,
+    budget: Res<Budget>,
+    mut previews: Query<(&mut Transform, &mut Visibility), With<TilePreview>>,
+    mut outlines: Query<&mut MeshMaterial3d<StandardMaterial>, With<PreviewOutline>>,
+    mut preview: Local<Option<(Entity, TileType)>>,
     mut egui_contexts: EguiContexts,
 ) {
-    if egui_contexts.ctx_mut().wants_pointer_input() {
-        return;
+    // Despawned with the previous game
+    if let Some((entity, _)) = *preview
+        && !previews.contains(entity)
+    {
+        *preview = None;
     }
-
-    if selected_tile.0 == TileType::Empty {
-        if let Some(entity) = *preview {
-            commands.entity(entity).despawn_recursive();
-            *preview = None;
-        }
+    if let Some((entity, tile_type)) = *preview
+        && tile_type != selected_tile.0
+    {
+        commands.entity(entity).despawn();
+        *preview = None;
+    }
+    if selected_tile.0 == TileType::EMPTY {
         return;
     }
-
-    let Ok(window) = windows.get_single() else {
+    let (entity, _) = *preview.get_or_insert_with(|| {
+        let entity = spawn_preview(
+            &mut commands,
+            &catalogue,
+            &tile_assets,
+            &highlight_materials,
+            tile_map.seed,
+            selected_tile.0,
+        );
+        (entity, selected_tile.0)
+    });
+    // Spawned this frame, shown from the next one
+    let Ok((mut transform, mut visibility)) = previews.get_mut(entity) else {
         return;
     };
-    let Ok((camera, camera_transform)) = camera.get_single() else {
+
+    let hovered = hovered
+        .0
+        .filter(|_| !egui_contexts.ctx_mut().wants_pointer_input());
+    let Some((x, z)) = hovered else {
+        visibility.set_if_neq(Visibility::Hidden);
         return;
     };
 
-    if let Some(cursor_pos) = window.cursor_position() {
-        if let Some(ray) = camera.viewport_to_world(camera_transform, cursor_pos).ok() {
-            let plane = InfinitePlane3d::new(Vec3::Y);
-            if let Some(distance) = ray.intersect_plane(Vec3::ZERO, plane) {
-                let intersection = ray.get_point(distance);
-                let x = intersection.x.round() as i32;
-                let z = intersection.z.round() as i32;
-
-                if x >= 0 && x < tile_map.width as i32 && z >= 0 && z < tile_map.height as i32 {
-                    let x = x as usize;
-                    let z = z as usize;
-
-                    let can_place = wfc_state.grid.can_place_tile(x, z, selected_tile.0);
-                    let tile_handle = tile_assets.tiles[selected_tile.0.index()].clone();
-
-                    if mouse_input.just_pressed(MouseButton::Left) && can_place {
-                        if place_tile(
-                            &mut commands,
-                            &mut tile_map,
-                            &mut wfc_state,
-                            &tile_assets,
-                            &selected_tile,
-                            &mut undo_redo,
-                            x,
-                            z,
-                        ) {
-                            if let Some(entity) = *preview {
-                                commands.entity(entity).despawn_recursive();
-                                *preview = None;
-                            }
-                            selected_tile.0 = TileType::Empty;
-                        }
-                    } else {
-                        if let Some(entity) = *preview {
-                            commands.entity(entity).despawn_recursive();
-                        }
-                        *preview = Some(
-                            commands
-                                .spawn((
-                                    SceneRoot(tile_handle),
-                                    Transform {
-                                        translation: Vec3::new(x as f32, 0.01, z as f32),
-                                        scale: selected_tile.0.scale(),
-                                        ..default()
-                                    },
-                                ))
-                                .id(),
-                        );
-                    }
+    let placement = wfc_state.grid.check_placement(x, z, selected_tile.0);
+    let shortfall = budget.shortfall(catalogue.get(selected_tile.0));
+    let can_place = placement.is_ok() && shortfall.is_none();
+
+    // Explain why the preview is red
+    if let Some(shortfall) = shortfall {
+        egui::show_tooltip_at_pointer(
+            egui_contexts.ctx_mut(),
+            egui::LayerId::background(),
+            egui::Id::new("placement_reason"),
+            |ui| {
+                ui.label(shortfall);
+            },
+        );
+    } else if let Err(reason) = placement {
+        egui::show_tooltip_at_pointer(
+            egui_contexts.ctx_mut(),
+            egui::LayerId::background(),
+            egui::Id::new("placement_reason"),
+            |ui| {
+                ui.label(format!("Cannot place {}", catalogue.name(selected_tile.0)));
+                ui.label(reason.to_string());
+                let possible: Vec<_> = wfc_state
+                    .grid
+                    .get_possible_tiles(x, z)
+                    .into_iter()
+                    .map(|tile_type| catalogue.name(tile_type))
+                    .collect();
+                if !possible.is_empty() {
+                    ui.label(format!("Still possible here: {}", possible.join(", ")));
                 }
-            }
+            },
+        );
+    }
+
+    visibility.set_if_neq(Visibility::Inherited);
+    transform.set_if_neq(Transform::from_xyz(x as f32, 0.01, z as f32));
+    let material = if can_place {
+        &highlight_materials.preview
+    } else {
+        &highlight_materials.invalid
+    };
+    for mut outline in outlines.iter_mut() {
+        if outline.0 != *material {
+            outline.0 = material.clone();
         }
     }
 }
 
-/// Places a tile at the given coordinates. Returns true if placement succeeded.
+/// Places a tile at the given coordinates. Fails with the reason it cannot be placed there,
+/// or cannot be paid for.
+#[allow(clippy::too_many_arguments)]
 pub fn place_tile(
     commands: &mut Commands,
     tile_map: &mut TileMap,
     wfc_state: &mut WFCState,
+    catalogue: &TileCatalogue,
     tile_assets: &TileAssets,
     selected_tile: &SelectedTile,
     undo_redo: &mut UndoRedo,
+    budget: &mut Budget,
     x: usize,
     z: usize,
-) -> bool {
-    if selected_tile.0 == TileType::Empty {
-        return false;
+) -> Result<(), String> {
+    let tile_type = selected_tile.0;
+    if tile_type == TileType::EMPTY {
+        return Err("No tile selected".to_string());
     }
+    let name = catalogue.name(tile_type);
 
-    if x >= tile_map.width || z >= tile_map.height {
-        return false;
+    wfc_state
+        .grid
+        .check_placement(x, z, tile_type)
+        .map_err(|err| format!("Cannot place {}: {}", name, err))?;
+
+    if !budget.charge(catalogue.get(tile_type)) {
+        return Err(budget.refused.clone().unwrap_or_default());
+    }
+    if wfc_state.grid.place_tile(x, z, tile_type) {
+        tile_map.set_tile(commands, tile_assets, x, z, tile_type, 0);
+        undo_redo.add_action(Action::PlaceTile(x, z, tile_type, 0));
+        return Ok(());
     }
 
-    if !wfc_state.grid.can_place_tile(x, z, selected_tile.0) {
-        return false;
+    budget.refund(catalogue.get(tile_type));
+    Err(format!(
+        "Cannot place {}: {}",
+        name,
+        PlacementError::Contradiction
+    ))
+}
+
+/// How long a refused placement stays explained next to the pointer, in seconds.
+const REFUSAL_TIME: f32 = 3.0;
+
+/// Why the last click could not place the selected tile.
+#[derive(Resource, Default)]
+pub struct PlacementRefusal {
+    pub message: Option<String>,
+    /// Pointer position of the click, in logical pixels.
+    pub position: Vec2,
+    /// Real time of the click, in seconds.
+    pub since: f32,
+}
+
+/// Shows the reason of a refused placement where the click happened, for `REFUSAL_TIME` seconds.
+pub fn show_placement_refusal(
+    mut contexts: EguiContexts,
+    mut refusal: ResMut<PlacementRefusal>,
+    time: Res<Time<Real>>,
+) {
+    let Some(message) = &refusal.message else {
+        return;
+    };
+    if time.elapsed_secs() - refusal.since > REFUSAL_TIME {
+        refusal.message = None;
+        return;
     }
+    let position = egui::pos2(refusal.position.x + 16.0, refusal.position.y + 16.0);
+    egui::Area::new(egui::Id::new("placement_refusal"))
+        .fixed_pos(position)
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.colored_label(egui::Color32::LIGHT_RED, message);
+            });
+        });
+}
+
+/// Removes the building anchored at (x, z). Returns false if there is none.
+pub fn demolish_tile(
+    commands: &mut Commands,
+    tile_map: &mut TileMap,
+    wfc_state: &mut WFCState,
+    undo_redo: &mut UndoRedo,
+    x: usize,
+    z: usize,
+) -> bool {
+    let rotation = tile_map.tiles[z][x].rotation;
+    let Some((x, z, tile_type)) = tile_map.remove_building(commands, x, z) else {
+        return false;
+    };
+    wfc_state.grid.remove_tile(x, z, tile_type);
+    undo_redo.add_action(Action::RemoveTile(x, z, tile_type, rotation));
+    true
+}
 
-    if wfc_state.grid.place_tile(x, z, selected_tile.0) {
-        let entity = commands
-            .spawn((
-                SceneRoot(tile_assets.tiles[selected_tile.0.index()].clone()),
-                Transform {
-                    translation: Vec3::new(x as f32, 0.0, z as f32),
-                    scale: selected_tile.0.scale(),
-                    ..default()
-                },
-            ))
-            .id();
-
-        tile_map.tiles[z][x].tile_type = selected_tile.0;
-        tile_map.entities[z][x] = Some(entity);
-        undo_redo.add_action(Action::PlaceTile(x, z, selected_tile.0));
-        return true;
+/// Swaps the building anchored at (x, z) for `new_type`, keeping its rotation, and pays for it.
+/// The old building stays when the new one does not fit. The caller checks it is affordable.
+#[allow(clippy::too_many_arguments)]
+pub fn replace_tile(
+    commands: &mut Commands,
+    tile_map: &mut TileMap,
+    wfc_state: &mut WFCState,
+    catalogue: &TileCatalogue,
+    tile_assets: &TileAssets,
+    undo_redo: &mut UndoRedo,
+    budget: &mut Budget,
+    x: usize,
+    z: usize,
+    new_type: TileType,
+) -> Result<(), PlacementError> {
+    let Tile {
+        tile_type: old_type,
+        rotation,
+        ..
+    } = tile_map.tiles[z][x];
+
+    // The new tile is checked against the grid without the old one
+    let snapshot = wfc_state.grid.cells.clone();
+    wfc_state.grid.remove_tile(x, z, old_type);
+    let placed = wfc_state
+        .grid
+        .check_placement(x, z, new_type)
+        .and_then(|()| {
+            if wfc_state.grid.place_tile(x, z, new_type) {
+                Ok(())
+            } else {
+                Err(PlacementError::Contradiction)
+            }
+        });
+    if let Err(err) = placed {
+        wfc_state.grid.cells = snapshot;
+        return Err(err);
     }
 
-    false
+    tile_map.remove_building(commands, x, z);
+    tile_map.set_tile(commands, tile_assets, x, z, new_type, rotation);
+    budget.pay(catalogue.get(new_type));
+    undo_redo.add_action(Action::ReplaceTile(x, z, old_type, new_type));
+    Ok(())
 }
 
+/// Shows where the selected tile can go. Only runs when the selection or the grid changes.
 pub fn update_placement_highlights(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
     wfc_state: Res<WFCState>,
     selected_tile: Res<SelectedTile>,
     highlight_materials: Res<HighlightMaterials>,
-    query: Query<Entity, With<PlacementHighlight>>,
+    mut highlights: Query<(
+        &PlacementHighlight,
+        &mut Visibility,
+        &mut MeshMaterial3d<StandardMaterial>,
+    )>,
 ) {
-    // Removes old highlights
-    for entity in query.iter() {
-        commands.entity(entity).despawn();
-    }
-
-    if selected_tile.0 == TileType::Empty {
+    if !(selected_tile.is_changed() || wfc_state.is_changed()) {
         return;
     }
 
-    let tile_mesh = meshes.add(Plane3d::default().mesh().size(1.0, 1.0));
-
-    // For each grid cell
-    for y in 0..wfc_state.grid.height {
-        for x in 0..wfc_state.grid.width {
-            let idx = wfc_state.grid.idx(x, y);
-            let cell = &wfc_state.grid.cells[idx];
-
-            if !cell.collapsed && cell.possible[selected_tile.0.index()] {
-                let material = if wfc_state.grid.can_place_tile(x, y, selected_tile.0) {
-                    highlight_materials.valid.clone()
-                } else {
-                    highlight_materials.invalid.clone()
-                };
-
-                commands.spawn((
-                    Mesh3d(tile_mesh.clone()),
-                    MeshMaterial3d(material),
-                    Transform::from_xyz(x as f32, 0.02, y as f32),
-                    PlacementHighlight,
-                ));
-            }
+    let grid = &wfc_state.grid;
+    for (highlight, mut visibility, mut material) in highlights.iter_mut() {
+        let cell = &grid.cells[grid.idx(highlight.x, highlight.z)];
+        if selected_tile.0 == TileType::EMPTY
+            || cell.collapsed
+            || !cell.possible[selected_tile.0.index()]
+        {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        }
+
+        let handle = if grid.can_place_tile(highlight.x, highlight.z, selected_tile.0) {
+            &highlight_materials.valid
+        } else {
+            &highlight_materials.invalid
+        };
+        visibility.set_if_neq(Visibility::Inherited);
+        if material.0 != *handle {
+            material.0 = handle.clone();
         }
     }
 }
@@ -1,8 +1,32 @@
-use crate::app_config::{BackgroundMusic, GameSettings, GameState, GraphicsQuality};
+use crate::app_config::{BackgroundMusic, GameSettings, GameState, GraphicsQuality, InGameState};
+use crate::chunks::WorldMode;
+use crate::save::{LoadMap, SaveFile};
+use crate::tile_loader::{LogLevel, TileAssets, TileCatalogue, TileLog};
 use bevy::audio::Volume;
 use bevy::prelude::*;
 use bevy_egui::{EguiContexts, egui};
 
+pub fn loading_screen(
+    mut contexts: EguiContexts,
+    asset_server: Res<AssetServer>,
+    catalogue: Res<TileCatalogue>,
+    tile_assets: Res<TileAssets>,
+) {
+    let (done, total) = tile_assets.progress(&asset_server, &catalogue);
+
+    egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
+        ui.vertical_centered(|ui| {
+            ui.heading("Loading");
+            ui.add_space(20.0);
+            ui.add(
+                egui::ProgressBar::new(done as f32 / total.max(1) as f32)
+                    .desired_width(300.0)
+                    .text(format!("{} / {} tile models", done, total)),
+            );
+        });
+    });
+}
+
 pub fn main_menu(
     /* mut commands: Commands,
     asset_server: Res<AssetServer>, */
@@ -23,6 +47,10 @@ pub fn main_menu(
                 next_state.set(GameState::Settings);
             }
 
+            if ui.button("Benchmark").clicked() {
+                next_state.set(GameState::Benchmark);
+            }
+
             ui.add_space(20.0);
 
             if ui.button("Quit").clicked() {
@@ -32,6 +60,51 @@ pub fn main_menu(
     });
 }
 
+/// Sliders shared by the settings menu and the in-game settings overlay.
+fn settings_controls(ui: &mut egui::Ui, settings: &mut GameSettings) {
+    ui.vertical_centered(|ui| {
+        ui.set_max_width(300.0);
+        ui.horizontal(|ui| {
+            ui.label("Volume:");
+            ui.add(egui::Slider::new(&mut settings.volume, 0.0..=1.0));
+        });
+    });
+
+    ui.vertical_centered(|ui| {
+        ui.set_max_width(300.0);
+        ui.horizontal(|ui| {
+            ui.label("Brightness:");
+            ui.add(egui::Slider::new(&mut settings.brightness, 0.0..=1.0));
+        });
+    });
+
+    ui.vertical_centered(|ui| {
+        ui.set_max_width(300.0);
+        ui.horizontal(|ui| {
+            ui.label("Graphics Quality:");
+            egui::ComboBox::from_label("")
+                .selected_text(format!("{:?}", settings.graphics_quality))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut settings.graphics_quality,
+                        GraphicsQuality::Low,
+                        "Low",
+                    );
+                    ui.selectable_value(
+                        &mut settings.graphics_quality,
+                        GraphicsQuality::Medium,
+                        "Medium",
+                    );
+                    ui.selectable_value(
+                        &mut settings.graphics_quality,
+                        GraphicsQuality::High,
+                        "High",
+                    );
+                });
+        });
+    });
+}
+
 pub fn settings_menu(
     mut contexts: EguiContexts,
     mut next_state: ResMut<NextState<GameState>>,
@@ -44,47 +117,7 @@ pub fn settings_menu(
 
             ui.add_space(20.0);
 
-            ui.vertical_centered(|ui| {
-                ui.set_max_width(300.0);
-                ui.horizontal(|ui| {
-                    ui.label("Volume:");
-                    ui.add(egui::Slider::new(&mut settings.volume, 0.0..=1.0));
-                });
-            });
-
-            ui.vertical_centered(|ui| {
-                ui.set_max_width(300.0);
-                ui.horizontal(|ui| {
-                    ui.label("Brightness:");
-                    ui.add(egui::Slider::new(&mut settings.brightness, 0.0..=1.0));
-                });
-            });
-
-            ui.vertical_centered(|ui| {
-                ui.set_max_width(300.0);
-                ui.horizontal(|ui| {
-                    ui.label("Graphics Quality:");
-                    egui::ComboBox::from_label("")
-                        .selected_text(format!("{:?}", settings.graphics_quality))
-                        .show_ui(ui, |ui| {
-                            ui.selectable_value(
-                                &mut settings.graphics_quality,
-                                GraphicsQuality::Low,
-                                "Low",
-                            );
-                            ui.selectable_value(
-                                &mut settings.graphics_quality,
-                                GraphicsQuality::Medium,
-                                "Medium",
-                            );
-                            ui.selectable_value(
-                                &mut settings.graphics_quality,
-                                GraphicsQuality::High,
-                                "High",
-                            );
-                        });
-                });
-            });
+            settings_controls(ui, &mut settings);
 
             ui.add_space(20.0);
 
@@ -95,20 +128,63 @@ pub fn settings_menu(
     });
 }
 
-pub fn load_game_menu(mut contexts: EguiContexts, mut next_state: ResMut<NextState<GameState>>) {
+/// Settings over the paused game, which stays in the `InGame` state underneath.
+/// Closing them goes back to the pause menu.
+pub fn ingame_settings(
+    mut contexts: EguiContexts,
+    mut next_state: ResMut<NextState<InGameState>>,
+    mut settings: ResMut<GameSettings>,
+) {
+    let response =
+        egui::Modal::new(egui::Id::new("ingame_settings")).show(contexts.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading("Settings");
+
+                ui.add_space(20.0);
+
+                settings_controls(ui, &mut settings);
+
+                ui.add_space(20.0);
+
+                ui.button("Back").clicked()
+            })
+            .inner
+        });
+    if response.inner || response.should_close() {
+        next_state.set(InGameState::Paused);
+    }
+}
+
+pub fn load_game_menu(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut world_mode: ResMut<WorldMode>,
+    save_file: Res<SaveFile>,
+) {
     egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
         ui.vertical_centered(|ui| {
             ui.heading("Welcome");
 
             ui.add_space(20.0);
 
-            // TODO: Handle load game & start game
-
-            if ui.button("Load Game").clicked() {
+            if ui
+                .add_enabled(save_file.map.is_some(), egui::Button::new("Load Game"))
+                .on_disabled_hover_text("No saved game yet")
+                .clicked()
+            {
+                commands.insert_resource(LoadMap);
+                *world_mode = WorldMode::Finite;
                 next_state.set(GameState::InGame);
             }
 
             if ui.button("New Game").clicked() {
+                *world_mode = WorldMode::Finite;
+                next_state.set(GameState::InGame);
+            }
+
+            if ui.button("New Infinite World").clicked() {
+                *world_mode = WorldMode::Infinite;
                 next_state.set(GameState::InGame);
             }
 
@@ -125,9 +201,35 @@ pub fn update_volume(
     settings: Res<GameSettings>,
     mut query: Query<&mut AudioSink, With<BackgroundMusic>>,
 ) {
-    if settings.is_changed() {
-        if let Ok(mut sink) = query.get_single_mut() {
-            sink.set_volume(Volume::Linear(settings.volume));
-        }
+    if settings.is_changed()
+        && let Ok(mut sink) = query.single_mut()
+    {
+        sink.set_volume(Volume::Linear(settings.volume));
+    }
+}
+
+/// Problems met while loading the tiles and tile packs. Opens by itself when something went wrong.
+pub fn tile_log_window(mut contexts: EguiContexts, mut log: ResMut<TileLog>) {
+    if !log.open {
+        return;
     }
+
+    let log = log.as_mut();
+    egui::Window::new("Tile loading")
+        .open(&mut log.open)
+        .default_width(400.0)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    for (level, message) in &log.entries {
+                        let color = match level {
+                            LogLevel::Info => egui::Color32::GRAY,
+                            LogLevel::Warning => egui::Color32::YELLOW,
+                            LogLevel::Error => egui::Color32::RED,
+                        };
+                        ui.colored_label(color, message);
+                    }
+                });
+        });
 }
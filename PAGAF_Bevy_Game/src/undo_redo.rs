@@ -1,12 +1,25 @@
 use bevy::prelude::*;
 
-use crate::tile_loader::TileAssets;
+use crate::budget::Budget;
+use crate::tile_loader::{TileAssets, TileCatalogue};
 use crate::tilemap::{TileMap, TileType};
+use crate::wfc::WFCState;
 
+/// Actions are stored with the anchor cell of the tile, the whole footprint is affected.
+/// Undoing a construction refunds it, redoing it pays again.
 #[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
 pub enum Action {
-    PlaceTile(usize, usize, TileType),
-    RemoveTile(usize, usize, TileType),
+    /// Type and rotation of the placed tile.
+    PlaceTile(usize, usize, TileType, u8),
+    /// Type and rotation of the removed tile.
+    RemoveTile(usize, usize, TileType, u8),
+    /// Old and new type. The rotation is kept.
+    ReplaceTile(usize, usize, TileType, TileType),
+    /// One quarter turn clockwise.
+    RotateTile(usize, usize),
+    /// Actions undone and redone together, such as a pasted blueprint.
+    Group(Vec<Action>),
 }
 
 #[derive(Resource, Default)]
@@ -21,62 +34,219 @@ impl UndoRedo {
         self.redo_stack.clear();
     }
 
-    pub fn undo(&mut self, tilemap: &mut TileMap, commands: &mut Commands) {
+    pub fn undo(
+        &mut self,
+        tilemap: &mut TileMap,
+        wfc_state: &mut WFCState,
+        commands: &mut Commands,
+        catalogue: &TileCatalogue,
+        tile_assets: &TileAssets,
+        budget: &mut Budget,
+    ) {
         if let Some(action) = self.history.pop() {
-            match &action {
-                Action::PlaceTile(x, y, _) => {
-                    self.redo_stack.push(action.clone());
-
-                    // Remove visual tile if present
-                    if let Some(entity) = tilemap.entities[*y][*x].take() {
-                        commands.entity(entity).despawn_recursive();
-                    }
-
-                    tilemap.tiles[*y][*x].tile_type = TileType::Empty;
-                }
-                Action::RemoveTile(x, y, old_type) => {
-                    self.redo_stack.push(action.clone());
-                    tilemap.tiles[*y][*x].tile_type = *old_type;
-                }
-            }
+            undo_action(
+                &action,
+                tilemap,
+                wfc_state,
+                commands,
+                catalogue,
+                tile_assets,
+                budget,
+            );
+            self.redo_stack.push(action);
         }
     }
 
     pub fn redo(
         &mut self,
         tilemap: &mut TileMap,
+        wfc_state: &mut WFCState,
         commands: &mut Commands,
-        tile_assets: &Res<TileAssets>,
+        catalogue: &TileCatalogue,
+        tile_assets: &TileAssets,
+        budget: &mut Budget,
     ) {
         if let Some(action) = self.redo_stack.pop() {
-            match &action {
-                Action::PlaceTile(x, y, tile_type) => {
-                    self.history.push(action.clone());
-                    tilemap.tiles[*y][*x].tile_type = *tile_type;
+            redo_action(
+                &action,
+                tilemap,
+                wfc_state,
+                commands,
+                catalogue,
+                tile_assets,
+                budget,
+            );
+            self.history.push(action);
+        }
+    }
 
-                    let handle = tile_assets.tiles[tile_type.index()].clone();
-                    let entity = commands
-                        .spawn((
-                            SceneRoot(handle),
-                            Transform {
-                                translation: Vec3::new(*x as f32, 0.0, *y as f32),
-                                scale: tile_type.scale(),
-                                ..default()
-                            },
-                        ))
-                        .id();
-                    tilemap.entities[*y][*x] = Some(entity);
+    /// Position in `history` of the action that put the building anchored at (x, y) in place.
+    /// `None` for tiles placed by the map generator.
+    pub fn placed_at(&self, x: usize, y: usize, tile_type: TileType) -> Option<usize> {
+        fn places(action: &Action, x: usize, y: usize, tile_type: TileType) -> bool {
+            match *action {
+                Action::PlaceTile(ax, ay, t, _) | Action::ReplaceTile(ax, ay, _, t) => {
+                    (ax, ay, t) == (x, y, tile_type)
                 }
-                Action::RemoveTile(x, y, _) => {
-                    self.history.push(action.clone());
+                Action::Group(ref actions) => actions.iter().any(|a| places(a, x, y, tile_type)),
+                _ => false,
+            }
+        }
+        self.history
+            .iter()
+            .rposition(|action| places(action, x, y, tile_type))
+    }
+}
 
-                    if let Some(entity) = tilemap.entities[*y][*x].take() {
-                        commands.entity(entity).despawn_recursive();
-                    }
+fn undo_action(
+    action: &Action,
+    tilemap: &mut TileMap,
+    wfc_state: &mut WFCState,
+    commands: &mut Commands,
+    catalogue: &TileCatalogue,
+    tile_assets: &TileAssets,
+    budget: &mut Budget,
+) {
+    match action {
+        Action::PlaceTile(x, y, tile_type, _) => {
+            remove(tilemap, wfc_state, commands, *x, *y, *tile_type);
+            budget.refund(catalogue.get(*tile_type));
+        }
+        Action::RemoveTile(x, y, old_type, rotation) => {
+            place(
+                tilemap,
+                wfc_state,
+                commands,
+                tile_assets,
+                *x,
+                *y,
+                *old_type,
+                *rotation,
+            );
+        }
+        Action::ReplaceTile(x, y, old_type, new_type) => {
+            let rotation = tilemap.tiles[*y][*x].rotation;
+            remove(tilemap, wfc_state, commands, *x, *y, *new_type);
+            budget.refund(catalogue.get(*new_type));
+            place(
+                tilemap,
+                wfc_state,
+                commands,
+                tile_assets,
+                *x,
+                *y,
+                *old_type,
+                rotation,
+            );
+        }
+        Action::RotateTile(x, y) => {
+            let rotation = tilemap.tiles[*y][*x].rotation;
+            tilemap.set_rotation(commands, tile_assets, *x, *y, rotation + 3);
+        }
+        Action::Group(actions) => {
+            for action in actions.iter().rev() {
+                undo_action(
+                    action,
+                    tilemap,
+                    wfc_state,
+                    commands,
+                    catalogue,
+                    tile_assets,
+                    budget,
+                );
+            }
+        }
+    }
+}
 
-                    tilemap.tiles[*y][*x].tile_type = TileType::Empty;
-                }
+fn redo_action(
+    action: &Action,
+    tilemap: &mut TileMap,
+    wfc_state: &mut WFCState,
+    commands: &mut Commands,
+    catalogue: &TileCatalogue,
+    tile_assets: &TileAssets,
+    budget: &mut Budget,
+) {
+    // Redoing does not check the treasury, it may go into debt
+    match action {
+        Action::PlaceTile(x, y, tile_type, rotation) => {
+            budget.pay(catalogue.get(*tile_type));
+            place(
+                tilemap,
+                wfc_state,
+                commands,
+                tile_assets,
+                *x,
+                *y,
+                *tile_type,
+                *rotation,
+            );
+        }
+        Action::RemoveTile(x, y, tile_type, _) => {
+            remove(tilemap, wfc_state, commands, *x, *y, *tile_type);
+        }
+        Action::ReplaceTile(x, y, old_type, new_type) => {
+            let rotation = tilemap.tiles[*y][*x].rotation;
+            remove(tilemap, wfc_state, commands, *x, *y, *old_type);
+            budget.pay(catalogue.get(*new_type));
+            place(
+                tilemap,
+                wfc_state,
+                commands,
+                tile_assets,
+                *x,
+                *y,
+                *new_type,
+                rotation,
+            );
+        }
+        Action::RotateTile(x, y) => {
+            let rotation = tilemap.tiles[*y][*x].rotation;
+            tilemap.set_rotation(commands, tile_assets, *x, *y, rotation + 1);
+        }
+        Action::Group(actions) => {
+            for action in actions {
+                redo_action(
+                    action,
+                    tilemap,
+                    wfc_state,
+                    commands,
+                    catalogue,
+                    tile_assets,
+                    budget,
+                );
             }
         }
     }
 }
+
+#[allow(clippy::too_many_arguments)]
+fn place(
+    tilemap: &mut TileMap,
+    wfc_state: &mut WFCState,
+    commands: &mut Commands,
+    tile_assets: &TileAssets,
+    x: usize,
+    y: usize,
+    tile_type: TileType,
+    rotation: u8,
+) {
+    if wfc_state.grid.place_tile(x, y, tile_type) {
+        tilemap.set_tile(commands, tile_assets, x, y, tile_type, rotation);
+    } else {
+        warn!("Cannot restore the building at ({}, {})", x, y);
+    }
+}
+
+fn remove(
+    tilemap: &mut TileMap,
+    wfc_state: &mut WFCState,
+    commands: &mut Commands,
+    x: usize,
+    y: usize,
+    tile_type: TileType,
+) {
+    tilemap.remove_building(commands, x, y);
+    wfc_state.grid.remove_tile(x, y, tile_type);
+}
@@ -1,72 +1,159 @@
-use crate::tile_loader::TileAssets;
-use crate::tilemap::{TileMap, TileType};
+use crate::tile_loader::TileCatalogue;
+use crate::tilemap::TileType;
 use bevy::prelude::*;
 use rand::distr::weighted;
 use rand::prelude::*;
-use rand::thread_rng;
+use rand::rng;
 use std::collections::VecDeque;
+use std::fmt;
 
 /* ─────────────────────────────  Constants  ──────────────────────────────── */
 
-const TILE_COUNT: usize = TileType::Park as usize + 1;
-const WEIGHTS: [f32; TILE_COUNT] = [0.0, 3.0, 2.0, 1.0, 2.5, 1.5];
-
-const NORTH: usize = 0;
-const SOUTH: usize = 1;
-const EAST: usize = 2;
-const WEST: usize = 3;
-
-// Règles de placement des tuiles
-const RULES: [[[bool; TILE_COUNT]; TILE_COUNT]; 4] = build_rules();
-
-const fn build_rules() -> [[[bool; TILE_COUNT]; TILE_COUNT]; 4] {
-    let mut m = [[[true; TILE_COUNT]; TILE_COUNT]; 4];
-    let res = TileType::Residential as usize;
-    let ind = TileType::Industrial as usize;
-    let park = TileType::Park as usize;
-    let mut d = 0;
-    while d < 4 {
-        // Residential and industrial cannot be adjacent
-        m[d][res][ind] = false;
-        m[d][ind][res] = false;
-        // Parks cannot be adjacent to each other
-        m[d][park][park] = false;
-        d += 1;
-    }
-    m
+pub const NORTH: usize = 0;
+pub const SOUTH: usize = 1;
+pub const EAST: usize = 2;
+pub const WEST: usize = 3;
+
+/// Direction pointing back, from the neighbour in direction `dir`.
+pub fn opposite(dir: usize) -> usize {
+    match dir {
+        NORTH => SOUTH,
+        SOUTH => NORTH,
+        EAST => WEST,
+        _ => EAST,
+    }
+}
+
+pub fn direction_name(dir: usize) -> &'static str {
+    match dir {
+        NORTH => "north",
+        SOUTH => "south",
+        EAST => "east",
+        WEST => "west",
+        _ => "?",
+    }
+}
+
+/// Reason why a tile cannot be placed on a cell.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlacementError {
+    /// Part of the footprint lies outside the grid.
+    OutOfBounds { cell: (usize, usize) },
+    /// The cell already holds a tile.
+    CellCollapsed { cell: (usize, usize), tile: String },
+    /// A collapsed neighbour forbids the tile.
+    RuleViolation {
+        neighbour: (usize, usize),
+        direction: usize,
+        neighbour_tile: String,
+        rule: String,
+    },
+    /// Propagating the tile would leave a cell without any possible tile.
+    Contradiction,
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlacementError::OutOfBounds { cell } => {
+                write!(f, "Cell ({}, {}) is outside the map", cell.0, cell.1)
+            }
+            PlacementError::CellCollapsed { cell, tile } => {
+                write!(
+                    f,
+                    "Cell ({}, {}) is already collapsed to {}",
+                    cell.0, cell.1, tile
+                )
+            }
+            PlacementError::RuleViolation {
+                neighbour,
+                direction,
+                neighbour_tile,
+                rule,
+            } => write!(
+                f,
+                "{} at ({}, {}) to the {}: {}",
+                neighbour_tile,
+                neighbour.0,
+                neighbour.1,
+                direction_name(*direction),
+                rule
+            ),
+            PlacementError::Contradiction => {
+                write!(f, "It would leave a nearby cell without any possible tile")
+            }
+        }
+    }
+}
+
+/// One domain reduction performed while propagating constraints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropagationStep {
+    pub cell: (usize, usize),
+    /// Name of the tile removed from the cell.
+    pub removed: String,
+    pub source: (usize, usize),
+    pub direction: usize,
 }
 
 #[derive(Debug)]
 pub enum WFCError {
-    Contradiction,
-    InvalidState,
+    /// A cell ran out of possible tiles. `chain` lists every reduction that led to it.
+    Contradiction {
+        cell: (usize, usize),
+        chain: Vec<PropagationStep>,
+    },
+}
+
+impl fmt::Display for WFCError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WFCError::Contradiction { cell, chain } => {
+                write!(
+                    f,
+                    "WFC contradiction at ({}, {}), propagation chain:",
+                    cell.0, cell.1
+                )?;
+                for step in chain {
+                    write!(
+                        f,
+                        "\n  ({}, {}) -> {} of it ({}, {}): removed {}",
+                        step.source.0,
+                        step.source.1,
+                        direction_name(step.direction),
+                        step.cell.0,
+                        step.cell.1,
+                        step.removed
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
 /// Represents a cell in the Wave Function Collapse algorithm
 #[derive(Clone, Debug)]
 pub struct WFCCell {
-    pub possible: [bool; TILE_COUNT],
+    /// Indexed by tile, see the tile catalogue.
+    pub possible: Vec<bool>,
     pub count: usize,
     pub collapsed: bool,
 }
 
 impl WFCCell {
-    fn new_full() -> Self {
-        let mut p = [false; TILE_COUNT];
-        let mut c = 0;
-        for i in 1..TILE_COUNT {
-            p[i] = true;
-            c += 1;
-        }
+    fn new_full(tile_count: usize) -> Self {
+        let mut p = vec![true; tile_count];
+        p[TileType::EMPTY.index()] = false;
         Self {
+            count: p.len() - 1,
             possible: p,
-            count: c,
             collapsed: false,
         }
     }
 
     fn set_to(&mut self, id: usize) {
-        self.possible = [false; TILE_COUNT];
+        self.possible.fill(false);
         self.possible[id] = true;
         self.count = 1;
         self.collapsed = true;
@@ -82,26 +169,22 @@ pub struct WFCState {
     pub grid: WFCGrid,
 }
 
-impl Default for WFCState {
-    fn default() -> Self {
-        Self {
-            grid: WFCGrid::new(50, 50), // Same size as TileMap // TODO: refactor it
-        }
-    }
-}
-
+#[derive(Clone)]
 pub struct WFCGrid {
     pub width: usize,
     pub height: usize,
     pub cells: Vec<WFCCell>,
+    /// Tiles and rules the cells are indexed by.
+    catalogue: TileCatalogue,
 }
 
 impl WFCGrid {
-    pub fn new(width: usize, height: usize) -> Self {
+    pub fn new(width: usize, height: usize, catalogue: &TileCatalogue) -> Self {
         Self {
             width,
             height,
-            cells: vec![WFCCell::new_full(); width * height],
+            cells: vec![WFCCell::new_full(catalogue.len()); width * height],
+            catalogue: catalogue.clone(),
         }
     }
 
@@ -114,13 +197,13 @@ impl WFCGrid {
         let mut candidates = Vec::new();
 
         self.cells.iter().enumerate().for_each(|(idx, cell)| {
-            if !cell.collapsed && cell.count > 1 {
+            if !cell.collapsed && cell.entropy() > 0 {
                 let x = idx % self.width;
                 let y = idx / self.width;
 
-                match cell.count.cmp(&min) {
+                match cell.entropy().cmp(&min) {
                     std::cmp::Ordering::Less => {
-                        min = cell.count;
+                        min = cell.entropy();
                         candidates = vec![(x, y)];
                     }
                     std::cmp::Ordering::Equal => {
@@ -131,102 +214,304 @@ impl WFCGrid {
             }
         });
 
-        candidates.choose(&mut thread_rng()).copied()
+        candidates.choose(&mut rng()).copied()
     }
 
-    fn collapse(&mut self, x: usize, y: usize) {
+    // Multi-cell buildings only come from the player, a single cell cannot hold them
+    fn collapse(&mut self, x: usize, y: usize) -> Result<(), WFCError> {
         let idx = self.idx(x, y);
         let mut choice = Vec::<usize>::new();
         let mut weight = Vec::<f32>::new();
 
-        for i in 1..TILE_COUNT {
-            if self.cells[idx].possible[i] {
+        for (i, tile) in self.catalogue.tiles().iter().enumerate().skip(1) {
+            if self.cells[idx].possible[i] && tile.footprint() == UVec2::ONE {
                 choice.push(i);
-                weight.push(WEIGHTS[i]);
+                weight.push(tile.weight);
             }
         }
+        if choice.is_empty() {
+            return Err(WFCError::Contradiction {
+                cell: (x, y),
+                chain: Vec::new(),
+            });
+        }
 
-        let dist = weighted::WeightedIndex::new(&weight).unwrap();
-        let pick = choice[dist.sample(&mut thread_rng())];
+        // Only tiles with no weight (placed by hand) may be left, take the first one then
+        let pick = match weighted::WeightedIndex::new(&weight) {
+            Ok(dist) => choice[dist.sample(&mut rng())],
+            Err(_) => choice[0],
+        };
         self.cells[idx].set_to(pick);
+        Ok(())
+    }
+
+    /// Collapses the lowest entropy cell. Returns `None` once every cell is collapsed.
+    pub fn collapse_next(&mut self) -> Result<Option<(usize, usize)>, WFCError> {
+        let Some((x, y)) = self.lowest_entropy() else {
+            return Ok(None);
+        };
+        self.collapse(x, y)?;
+        Ok(Some((x, y)))
+    }
+
+    /// Tile of a collapsed cell, `None` while the cell is still undecided.
+    pub fn tile_at(&self, x: usize, y: usize) -> Option<TileType> {
+        let idx = self.idx(x, y);
+        self.cells[idx].collapsed.then(|| self.collapsed_tile(idx))
     }
 
-    pub fn propagate(&mut self, sx: usize, sy: usize) -> bool {
+    /// Collapses every undecided cell.
+    pub fn generate(&mut self) -> Result<(), WFCError> {
+        while let Some((x, y)) = self.collapse_next()? {
+            self.propagate(x, y)?;
+        }
+        Ok(())
+    }
+
+    /// Restricts (x, y) to the tiles allowed next to `tile`, which lies outside
+    /// the grid in direction `dir`. Used to stitch generated chunks together.
+    pub fn constrain(
+        &mut self,
+        x: usize,
+        y: usize,
+        dir: usize,
+        tile: TileType,
+    ) -> Result<(), WFCError> {
+        let idx = self.idx(x, y);
+        let mut chain = Vec::new();
+
+        for s in 1..self.catalogue.len() {
+            if self.cells[idx].possible[s] && !self.catalogue.compatible(dir, s, tile.index()) {
+                self.cells[idx].possible[s] = false;
+                self.cells[idx].count -= 1;
+                chain.push(PropagationStep {
+                    cell: (x, y),
+                    removed: self.catalogue.get(TileType::new(s)).name.clone(),
+                    source: (x, y),
+                    direction: dir,
+                });
+            }
+        }
+
+        if self.cells[idx].count == 0 {
+            return Err(WFCError::Contradiction {
+                cell: (x, y),
+                chain,
+            });
+        }
+        self.propagate(x, y)
+    }
+
+    pub fn propagate(&mut self, sx: usize, sy: usize) -> Result<(), WFCError> {
         let mut queue = VecDeque::new();
+        let mut chain = Vec::new();
         queue.push_back((sx, sy));
 
         while let Some((x, y)) = queue.pop_front() {
-            let idx = self.idx(x, y);
-            for dir in 0..4 {
-                if let Some((nx, ny)) = neighbour(self.width, self.height, x, y, dir) {
-                    let nidx = self.idx(nx, ny);
-                    let mut changed = false;
-
-                    for t in 1..TILE_COUNT {
-                        if !self.cells[nidx].possible[t] {
-                            continue;
-                        }
-                        let mut ok = false;
-                        for s in 1..TILE_COUNT {
-                            if self.cells[idx].possible[s] && RULES[dir][s][t] {
-                                ok = true;
-                                break;
-                            }
-                        }
-                        if !ok {
-                            self.cells[nidx].possible[t] = false;
-                            self.cells[nidx].count -= 1;
-                            changed = true;
-                        }
-                    }
+            queue.extend(self.prune_neighbours(x, y, &mut chain)?);
+        }
+        Ok(())
+    }
+
+    /// Removes from the four neighbours of (x, y) every tile the rules no longer allow.
+    /// Returns the neighbours whose domain shrank.
+    fn prune_neighbours(
+        &mut self,
+        x: usize,
+        y: usize,
+        chain: &mut Vec<PropagationStep>,
+    ) -> Result<Vec<(usize, usize)>, WFCError> {
+        let idx = self.idx(x, y);
+        let catalogue = &self.catalogue;
+        let mut pruned = Vec::new();
 
-                    if self.cells[nidx].count == 0 {
-                        return false;
+        for dir in 0..4 {
+            if let Some((nx, ny)) = neighbour(self.width, self.height, x, y, dir) {
+                let nidx = self.idx(nx, ny);
+                let mut changed = false;
+
+                for t in 1..catalogue.len() {
+                    if !self.cells[nidx].possible[t] {
+                        continue;
                     }
-                    if changed {
-                        queue.push_back((nx, ny));
+                    let mut ok = false;
+                    for s in 1..catalogue.len() {
+                        if self.cells[idx].possible[s] && catalogue.compatible(dir, s, t) {
+                            ok = true;
+                            break;
+                        }
+                    }
+                    if !ok {
+                        self.cells[nidx].possible[t] = false;
+                        self.cells[nidx].count -= 1;
+                        changed = true;
+                        chain.push(PropagationStep {
+                            cell: (nx, ny),
+                            removed: catalogue.get(TileType::new(t)).name.clone(),
+                            source: (x, y),
+                            direction: dir,
+                        });
                     }
                 }
+
+                if self.cells[nidx].count == 0 {
+                    return Err(WFCError::Contradiction {
+                        cell: (nx, ny),
+                        chain: std::mem::take(chain),
+                    });
+                }
+                if changed {
+                    pruned.push((nx, ny));
+                }
             }
         }
-        true
+        Ok(pruned)
     }
 
+    /// Places a tile anchored at (x, y) over its whole footprint and propagates.
+    /// On contradiction the grid is left untouched.
     pub fn place_tile(&mut self, x: usize, y: usize, tile_type: TileType) -> bool {
-        let idx = self.idx(x, y);
-        if self.cells[idx].collapsed {
+        if self.check_footprint_free(x, y, tile_type).is_err() {
             return false;
         }
 
-        self.cells[idx].set_to(tile_type as usize);
-        self.propagate(x, y)
+        let snapshot = self.cells.clone();
+        let definition = self.catalogue.get(tile_type);
+        let footprint: Vec<_> = definition.footprint_cells(x, y).collect();
+        let name = definition.name.clone();
+        for &(cx, cy) in &footprint {
+            let idx = self.idx(cx, cy);
+            self.cells[idx].set_to(tile_type.index());
+        }
+        let result = footprint
+            .into_iter()
+            .try_for_each(|(cx, cy)| self.propagate(cx, cy));
+        match result {
+            Ok(()) => true,
+            Err(err) => {
+                warn!("Placing {} at ({}, {}) failed: {}", name, x, y, err);
+                self.cells = snapshot;
+                false
+            }
+        }
+    }
+
+    /// Removes the tile anchored at (x, y) and recomputes the domains of the freed cells.
+    pub fn remove_tile(&mut self, x: usize, y: usize, tile_type: TileType) {
+        for (cx, cy) in self.catalogue.get(tile_type).footprint_cells(x, y) {
+            if cx < self.width && cy < self.height {
+                let idx = self.idx(cx, cy);
+                self.cells[idx] = WFCCell::new_full(self.catalogue.len());
+            }
+        }
+        self.recompute_domains();
+    }
+
+    /// Rebuilds the domain of every undecided cell from the collapsed ones.
+    fn recompute_domains(&mut self) {
+        let tile_count = self.catalogue.len();
+        for cell in self.cells.iter_mut().filter(|c| !c.collapsed) {
+            *cell = WFCCell::new_full(tile_count);
+        }
+        for idx in 0..self.cells.len() {
+            if self.cells[idx].collapsed {
+                let (x, y) = (idx % self.width, idx / self.width);
+                if let Err(err) = self.propagate(x, y) {
+                    warn!("Recomputing domains failed: {}", err);
+                }
+            }
+        }
     }
 
     pub fn can_place_tile(&self, x: usize, y: usize, tile_type: TileType) -> bool {
-        let idx = self.idx(x, y);
-        if self.cells[idx].collapsed {
-            return false;
+        self.check_placement(x, y, tile_type).is_ok()
+    }
+
+    /// Same as `can_place_tile` but tells which cell and rule block the placement.
+    pub fn check_placement(
+        &self,
+        x: usize,
+        y: usize,
+        tile_type: TileType,
+    ) -> Result<(), PlacementError> {
+        self.check_footprint_free(x, y, tile_type)?;
+
+        // Checks whether the tile respects the rules with its neighbors outside the footprint
+        let footprint: Vec<_> = self
+            .catalogue
+            .get(tile_type)
+            .footprint_cells(x, y)
+            .collect();
+        for &(x, y) in &footprint {
+            self.check_neighbours(x, y, tile_type, &footprint)?;
         }
+        Ok(())
+    }
 
-        // Checks whether the tile respects the rules with its neighbors
+    /// Checks that every cell of the footprint anchored at (x, y) is inside the grid and empty.
+    fn check_footprint_free(
+        &self,
+        x: usize,
+        y: usize,
+        tile_type: TileType,
+    ) -> Result<(), PlacementError> {
+        for (cx, cy) in self.catalogue.get(tile_type).footprint_cells(x, y) {
+            if cx >= self.width || cy >= self.height {
+                return Err(PlacementError::OutOfBounds { cell: (cx, cy) });
+            }
+            let idx = self.idx(cx, cy);
+            if self.cells[idx].collapsed {
+                return Err(PlacementError::CellCollapsed {
+                    cell: (cx, cy),
+                    tile: self.catalogue.get(self.collapsed_tile(idx)).name.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_neighbours(
+        &self,
+        x: usize,
+        y: usize,
+        tile_type: TileType,
+        footprint: &[(usize, usize)],
+    ) -> Result<(), PlacementError> {
         for dir in 0..4 {
             if let Some((nx, ny)) = neighbour(self.width, self.height, x, y, dir) {
                 let nidx = self.idx(nx, ny);
-                if self.cells[nidx].collapsed {
+                if self.cells[nidx].collapsed && !footprint.contains(&(nx, ny)) {
                     let mut valid = false;
-                    for t in 1..TILE_COUNT {
-                        if self.cells[nidx].possible[t] && RULES[dir][tile_type as usize][t] {
+                    for t in 1..self.catalogue.len() {
+                        if self.cells[nidx].possible[t]
+                            && self.catalogue.compatible(dir, tile_type.index(), t)
+                        {
                             valid = true;
                             break;
                         }
                     }
                     if !valid {
-                        return false;
+                        let neighbour_tile = self.collapsed_tile(nidx);
+                        return Err(PlacementError::RuleViolation {
+                            neighbour: (nx, ny),
+                            direction: dir,
+                            neighbour_tile: self.catalogue.get(neighbour_tile).name.clone(),
+                            rule: self
+                                .catalogue
+                                .reason(dir, tile_type.index(), neighbour_tile.index())
+                                .to_string(),
+                        });
                     }
                 }
             }
         }
-        true
+        Ok(())
+    }
+
+    fn collapsed_tile(&self, idx: usize) -> TileType {
+        (1..self.catalogue.len())
+            .find(|&t| self.cells[idx].possible[t])
+            .map_or(TileType::EMPTY, TileType::new)
     }
 
     pub fn get_possible_tiles(&self, x: usize, y: usize) -> Vec<TileType> {
@@ -234,11 +519,9 @@ impl WFCGrid {
         let mut possible = Vec::new();
 
         if !self.cells[idx].collapsed {
-            for i in 1..TILE_COUNT {
+            for i in 1..self.catalogue.len() {
                 if self.cells[idx].possible[i] {
-                    if let Some(tile_type) = TileType::from_index(i) {
-                        possible.push(tile_type);
-                    }
+                    possible.push(TileType::new(i));
                 }
             }
         }
@@ -247,6 +530,77 @@ impl WFCGrid {
     }
 }
 
+/// Granularity of a step in the step-by-step generation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StepMode {
+    /// Collapse one cell and propagate until stable.
+    #[default]
+    Collapse,
+    /// Collapse one cell, then run propagation one wave (BFS layer) per step.
+    Wave,
+}
+
+/// What a single generation step changed.
+#[derive(Clone, Debug, Default)]
+pub struct WFCStep {
+    pub collapsed: Option<(usize, usize)>,
+    pub pruned: Vec<(usize, usize)>,
+}
+
+/// Drives the generation one step at a time, keeping the pending propagation
+/// frontier between steps.
+#[derive(Default)]
+pub struct WFCStepper {
+    frontier: Vec<(usize, usize)>,
+}
+
+impl WFCStepper {
+    pub fn is_propagating(&self) -> bool {
+        !self.frontier.is_empty()
+    }
+
+    pub fn reset(&mut self) {
+        self.frontier.clear();
+    }
+
+    /// Advances the generation by one step. Returns `Ok(None)` once the grid is fully collapsed.
+    pub fn step(
+        &mut self,
+        grid: &mut WFCGrid,
+        mode: StepMode,
+    ) -> Result<Option<WFCStep>, WFCError> {
+        let mut step = WFCStep::default();
+
+        if self.frontier.is_empty() {
+            let Some((x, y)) = grid.collapse_next()? else {
+                return Ok(None);
+            };
+            step.collapsed = Some((x, y));
+            self.frontier.push((x, y));
+        }
+
+        let mut chain = Vec::new();
+        loop {
+            let mut next = Vec::new();
+            for (x, y) in std::mem::take(&mut self.frontier) {
+                for cell in grid.prune_neighbours(x, y, &mut chain)? {
+                    if !next.contains(&cell) {
+                        next.push(cell);
+                    }
+                }
+            }
+            step.pruned.extend(next.iter().copied());
+            self.frontier = next;
+
+            if mode == StepMode::Wave || self.frontier.is_empty() {
+                break;
+            }
+        }
+
+        Ok(Some(step))
+    }
+}
+
 /// Returns the neighbor's coordinates in the specified direction
 fn neighbour(w: usize, h: usize, x: usize, y: usize, dir: usize) -> Option<(usize, usize)> {
     match dir {
@@ -262,10 +616,19 @@ fn neighbour(w: usize, h: usize, x: usize, y: usize, dir: usize) -> Option<(usiz
 mod tests {
     use super::*;
 
+    fn tile(id: &str) -> TileType {
+        TileCatalogue::builtin().find(id).unwrap()
+    }
+
+    fn grid(width: usize, height: usize) -> WFCGrid {
+        WFCGrid::new(width, height, &TileCatalogue::builtin())
+    }
+
     #[test]
     fn test_wfc_cell_new() {
-        let cell = WFCCell::new_full();
-        assert_eq!(cell.count, TILE_COUNT - 1);
+        let tile_count = TileCatalogue::builtin().len();
+        let cell = WFCCell::new_full(tile_count);
+        assert_eq!(cell.count, tile_count - 1);
         assert!(!cell.collapsed);
     }
 
@@ -278,13 +641,143 @@ mod tests {
 
     #[test]
     fn test_can_place_tile() {
-        let mut grid = WFCGrid::new(3, 3);
+        let mut grid = grid(3, 3);
 
         // Initial placement test
-        assert!(grid.can_place_tile(1, 1, TileType::Residential));
+        assert!(grid.can_place_tile(1, 1, tile("residential")));
 
         // Place a tile and check constraints
-        grid.place_tile(1, 1, TileType::Residential);
-        assert!(!grid.can_place_tile(1, 1, TileType::Industrial));
+        grid.place_tile(1, 1, tile("residential"));
+        assert!(!grid.can_place_tile(1, 1, tile("industrial")));
+    }
+
+    #[test]
+    fn test_check_placement_reasons() {
+        let mut grid = grid(3, 3);
+        grid.place_tile(1, 1, tile("residential"));
+
+        assert_eq!(
+            grid.check_placement(1, 1, tile("park")),
+            Err(PlacementError::CellCollapsed {
+                cell: (1, 1),
+                tile: "Residential".to_string(),
+            })
+        );
+
+        // (1, 1) is south of (1, 0)
+        match grid.check_placement(1, 0, tile("industrial")) {
+            Err(PlacementError::RuleViolation {
+                neighbour,
+                direction,
+                neighbour_tile,
+                ..
+            }) => {
+                assert_eq!(neighbour, (1, 1));
+                assert_eq!(direction, SOUTH);
+                assert_eq!(neighbour_tile, "Residential");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_stepper_collapses_whole_grid() {
+        let mut grid = grid(4, 4);
+        let mut stepper = WFCStepper::default();
+        let mut steps = 0;
+
+        while let Some(step) = stepper.step(&mut grid, StepMode::Wave).unwrap() {
+            if step.collapsed.is_some() {
+                steps += 1;
+            }
+            assert!(steps <= 16);
+        }
+
+        assert!(!stepper.is_propagating());
+        for y in 0..4 {
+            for x in 0..4 {
+                assert!(grid.tile_at(x, y).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn test_constrain_from_outside_neighbour() {
+        let mut grid = grid(2, 2);
+        // An industrial tile lies north of the grid, above (0, 0)
+        grid.constrain(0, 0, NORTH, tile("industrial")).unwrap();
+        assert!(!grid.get_possible_tiles(0, 0).contains(&tile("residential")));
+        assert!(grid.get_possible_tiles(1, 0).contains(&tile("residential")));
+
+        grid.generate().unwrap();
+        assert_ne!(grid.tile_at(0, 0), Some(tile("residential")));
+    }
+
+    #[test]
+    fn test_multi_cell_footprint() {
+        let mut grid = grid(4, 4);
+
+        // A 2×2 stadium does not fit in the last column
+        assert_eq!(
+            grid.check_placement(3, 0, tile("stadium")),
+            Err(PlacementError::OutOfBounds { cell: (4, 0) })
+        );
+
+        assert!(grid.place_tile(1, 1, tile("stadium")));
+        for (x, y) in [(1, 1), (2, 1), (1, 2), (2, 2)] {
+            assert_eq!(grid.tile_at(x, y), Some(tile("stadium")));
+        }
+        assert!(!grid.can_place_tile(0, 0, tile("stadium")));
+
+        assert!(matches!(
+            grid.check_placement(0, 1, tile("mall")),
+            Err(PlacementError::CellCollapsed { .. })
+        ));
+
+        grid.remove_tile(1, 1, tile("stadium"));
+        assert_eq!(grid.tile_at(2, 2), None);
+        assert!(grid.can_place_tile(1, 1, tile("stadium")));
+
+        // The whole footprint of a factory complex is checked against its neighbours
+        assert!(grid.place_tile(0, 0, tile("residential")));
+        match grid.check_placement(1, 0, tile("factory_complex")) {
+            Err(PlacementError::RuleViolation { neighbour, .. }) => assert_eq!(neighbour, (0, 0)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(grid.can_place_tile(2, 1, tile("factory_complex")));
+    }
+
+    #[test]
+    fn test_contradiction_reports_chain() {
+        let mut grid = grid(3, 1);
+        // Middle cell can only be a park, surrounded by parks
+        let mid = grid.idx(1, 0);
+        grid.cells[mid].possible.fill(false);
+        grid.cells[mid].possible[tile("park").index()] = true;
+        grid.cells[mid].count = 1;
+        grid.cells[0].set_to(tile("park").index());
+
+        match grid.propagate(0, 0) {
+            Err(WFCError::Contradiction { cell, chain }) => {
+                assert_eq!(cell, (1, 0));
+                assert_eq!(chain.last().unwrap().removed, "Park");
+            }
+            other => panic!("expected a contradiction, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_collapse_skips_multi_cell_tiles() {
+        let mut grid = grid(1, 1);
+        // Only a 2×2 building is left for the single cell
+        grid.cells[0].possible.fill(false);
+        grid.cells[0].possible[tile("factory_complex").index()] = true;
+        grid.cells[0].count = 1;
+
+        assert!(matches!(
+            grid.collapse_next(),
+            Err(WFCError::Contradiction { cell: (0, 0), .. })
+        ));
+        assert_eq!(grid.tile_at(0, 0), None);
     }
 }
@@ -0,0 +1,281 @@
+use crate::app_config::GameState;
+use crate::tile_loader::{TileAssets, TileCatalogue};
+use crate::tilemap::TileMap;
+use crate::wfc::{StepMode, WFCState, WFCStep, WFCStepper};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+/// Step-by-step WFC visualisation, used to teach the algorithm.
+/// F5 toggles the mode, N advances one step.
+#[derive(Resource, Default)]
+pub struct WFCDebug {
+    pub enabled: bool,
+    pub auto_step: bool,
+    pub mode: StepMode,
+    pub show_numbers: bool,
+    pub last_step: WFCStep,
+    pub status: String,
+    stepper: WFCStepper,
+    step_requested: bool,
+}
+
+// Overlay quad drawn above each grid cell
+#[derive(Component)]
+pub struct DomainOverlay {
+    x: usize,
+    z: usize,
+}
+
+#[derive(Resource)]
+pub struct DomainMaterials {
+    /// Indexed by the number of tiles still possible in the cell.
+    by_count: Vec<Handle<StandardMaterial>>,
+    collapsed: Handle<StandardMaterial>,
+    pruned: Handle<StandardMaterial>,
+}
+
+pub fn setup_wfc_debug(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    wfc_state: Res<WFCState>,
+) {
+    let max = wfc_state.grid.cells.first().map_or(1, |c| c.count).max(1);
+    let by_count = (0..=max)
+        .map(|count| {
+            // Red for contradictions, then blue (few options) to green (all options)
+            let color = if count == 0 {
+                Color::srgba(1.0, 0.0, 0.0, 0.6)
+            } else {
+                let t = (count - 1) as f32 / (max.max(2) - 1) as f32;
+                Color::srgba(0.1, 0.2 + 0.7 * t, 1.0 - 0.8 * t, 0.45)
+            };
+            materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            })
+        })
+        .collect();
+
+    commands.insert_resource(DomainMaterials {
+        by_count,
+        collapsed: materials.add(StandardMaterial {
+            base_color: Color::srgba(1.0, 1.0, 0.0, 0.7),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+        pruned: materials.add(StandardMaterial {
+            base_color: Color::srgba(1.0, 0.5, 0.0, 0.6),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn toggle_wfc_debug(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    mut debug: ResMut<WFCDebug>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    wfc_state: Res<WFCState>,
+    domain_materials: Res<DomainMaterials>,
+    overlays: Query<Entity, With<DomainOverlay>>,
+    mut egui_contexts: EguiContexts,
+) {
+    // Typing in a text field, such as the blueprint name, must not step the generation
+    if !egui_contexts.ctx_mut().wants_keyboard_input() {
+        if input.just_pressed(KeyCode::F5) {
+            debug.enabled = !debug.enabled;
+        }
+        if input.just_pressed(KeyCode::KeyN) && debug.enabled {
+            debug.step_requested = true;
+        }
+    }
+
+    if !debug.is_changed() {
+        return;
+    }
+
+    if debug.enabled && overlays.is_empty() {
+        let mesh = meshes.add(Plane3d::default().mesh().size(0.9, 0.9));
+        let material = domain_materials.by_count.last().unwrap().clone();
+        for z in 0..wfc_state.grid.height {
+            for x in 0..wfc_state.grid.width {
+                commands.spawn((
+                    Mesh3d(mesh.clone()),
+                    MeshMaterial3d(material.clone()),
+                    Transform::from_xyz(x as f32, 0.03, z as f32),
+                    DomainOverlay { x, z },
+                    StateScoped(GameState::InGame),
+                ));
+            }
+        }
+    } else if !debug.enabled {
+        for entity in overlays.iter() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Advances the generation by one step per key press, or per frame in auto mode.
+/// Every collapsed cell is spawned in the tile map, for free like the generated map.
+pub fn step_wfc_debug(
+    mut commands: Commands,
+    mut debug: ResMut<WFCDebug>,
+    mut wfc_state: ResMut<WFCState>,
+    mut tile_map: ResMut<TileMap>,
+    catalogue: Res<TileCatalogue>,
+    tile_assets: Res<TileAssets>,
+) {
+    if !debug.enabled || !(debug.auto_step || debug.step_requested) {
+        return;
+    }
+    debug.step_requested = false;
+
+    let debug = debug.as_mut();
+    match debug.stepper.step(&mut wfc_state.grid, debug.mode) {
+        Ok(Some(step)) => {
+            if let Some((x, z)) = step.collapsed
+                && let Some(tile_type) = wfc_state.grid.tile_at(x, z)
+            {
+                tile_map.set_tile(&mut commands, &tile_assets, x, z, tile_type, 0);
+                debug.status = format!("Collapsed ({}, {}) to {}", x, z, catalogue.name(tile_type));
+            } else {
+                debug.status = format!("Propagation wave pruned {} cells", step.pruned.len());
+            }
+            debug.last_step = step;
+        }
+        Ok(None) => {
+            debug.auto_step = false;
+            debug.status = "Generation complete".to_string();
+            debug.last_step = WFCStep::default();
+        }
+        Err(err) => {
+            warn!("{}", err);
+            debug.auto_step = false;
+            debug.stepper.reset();
+            debug.status = "Contradiction, see log for the propagation chain".to_string();
+        }
+    }
+}
+
+pub fn update_domain_overlay(
+    debug: Res<WFCDebug>,
+    wfc_state: Res<WFCState>,
+    domain_materials: Res<DomainMaterials>,
+    mut overlays: Query<(
+        &DomainOverlay,
+        &mut Visibility,
+        &mut MeshMaterial3d<StandardMaterial>,
+    )>,
+) {
+    if !debug.enabled || !(debug.is_changed() || wfc_state.is_changed()) {
+        return;
+    }
+
+    for (overlay, mut visibility, mut material) in overlays.iter_mut() {
+        let cell = &wfc_state.grid.cells[wfc_state.grid.idx(overlay.x, overlay.z)];
+        let cell_pos = (overlay.x, overlay.z);
+
+        let handle = if debug.last_step.collapsed == Some(cell_pos) {
+            domain_materials.collapsed.clone()
+        } else if debug.last_step.pruned.contains(&cell_pos) {
+            domain_materials.pruned.clone()
+        } else if cell.collapsed {
+            *visibility = Visibility::Hidden;
+            continue;
+        } else {
+            domain_materials.by_count[cell.count.min(domain_materials.by_count.len() - 1)].clone()
+        };
+
+        *visibility = Visibility::Inherited;
+        if material.0 != handle {
+            material.0 = handle;
+        }
+    }
+}
+
+/// Writes the remaining domain size on top of every undecided cell.
+pub fn draw_domain_numbers(
+    mut contexts: EguiContexts,
+    debug: Res<WFCDebug>,
+    wfc_state: Res<WFCState>,
+    camera: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+) {
+    if !debug.enabled || !debug.show_numbers {
+        return;
+    }
+    let Ok((camera, camera_transform)) = camera.single() else {
+        return;
+    };
+
+    let ctx = contexts.ctx_mut();
+    let painter = ctx.layer_painter(egui::LayerId::background());
+    let grid = &wfc_state.grid;
+
+    for z in 0..grid.height {
+        for x in 0..grid.width {
+            let cell = &grid.cells[grid.idx(x, z)];
+            if cell.collapsed {
+                continue;
+            }
+            let world = Vec3::new(x as f32, 0.0, z as f32);
+            if let Ok(pos) = camera.world_to_viewport(camera_transform, world) {
+                painter.text(
+                    egui::pos2(pos.x, pos.y),
+                    egui::Align2::CENTER_CENTER,
+                    cell.count.to_string(),
+                    egui::FontId::monospace(12.0),
+                    egui::Color32::WHITE,
+                );
+            }
+        }
+    }
+}
+
+pub fn wfc_debug_panel(mut contexts: EguiContexts, mut debug: ResMut<WFCDebug>) {
+    if !debug.enabled {
+        return;
+    }
+
+    egui::Window::new("WFC Debug")
+        .anchor(egui::Align2::LEFT_TOP, egui::vec2(10.0, 10.0))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut debug.mode, StepMode::Collapse, "Per collapse");
+                ui.radio_value(&mut debug.mode, StepMode::Wave, "Per wave");
+            });
+            ui.checkbox(&mut debug.auto_step, "Auto (one step per frame)");
+            ui.checkbox(&mut debug.show_numbers, "Show domain sizes");
+
+            ui.horizontal(|ui| {
+                if ui.button("Step (N)").clicked() {
+                    debug.step_requested = true;
+                }
+                // Only the visualisation, the city and a running propagation are kept
+                if ui
+                    .button("Reset")
+                    .on_hover_text("Clears the step highlights, the city is kept")
+                    .clicked()
+                {
+                    debug.last_step = WFCStep::default();
+                    debug.status.clear();
+                }
+            });
+
+            if debug.stepper.is_propagating() {
+                ui.label("Propagating...");
+            }
+            ui.label(&debug.status);
+
+            ui.separator();
+            ui.label("Yellow: just collapsed");
+            ui.label("Orange: just pruned");
+            ui.label("Blue to green: few to many options left");
+            ui.label("Red: contradiction");
+        });
+}